@@ -1,6 +1,4 @@
 use std::path::Path;
-use std::fs::File;
-use std::process::exit;
 
 fn file_is_present(val: String) -> Result<(), String> {
     let path = Path::new(&val);
@@ -11,18 +9,26 @@ fn file_is_present(val: String) -> Result<(), String> {
     }
 }
 
-pub fn get_args() -> Option<File> {
+pub struct Args {
+    // None starts the repl
+    pub path: Option<String>,
+    pub print_result: bool,
+    pub prelude: bool,
+}
+
+pub fn get_args() -> Args {
     let matches = clap_app!(myapp =>
         (version: "0.1")
         (author: "Florian Lackner <lacknerflo@gmail.com>")
         (about: "Interprets Scheme code")
+        (@arg PRINT_RESULT: -p --("print-result") "Prints the result of the last expression in the file")
+        (@arg NO_PRELUDE: --("no-prelude") "Starts without the standard procedures written in flip")
         (@arg INPUT: {file_is_present} "File to interpret")
     ).get_matches();
 
-    matches.value_of("INPUT")
-    .map(File::open)
-    .map(|x| x.unwrap_or_else(|err| {
-        println!("Error opening file: {}", err);
-        exit(-1);
-    }))
+    Args {
+        path: matches.value_of("INPUT").map(String::from),
+        print_result: matches.is_present("PRINT_RESULT"),
+        prelude: !matches.is_present("NO_PRELUDE"),
+    }
 }
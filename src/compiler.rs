@@ -0,0 +1,203 @@
+use ::value::{Value, Pattern, MatchClause, QuasiTemplate, SyntaxRules};
+use ::string_interner::StringInterner;
+
+// Turns data into code like the parser produces it from the same source: lists starting with the name
+// of a special form become that special form. Macro expansions and eval get their code this way.
+// Returns the part of data which isn't valid code, if there is one.
+pub fn compile(data: &Value, interner: &mut StringInterner) -> Result<Value, Value> {
+    compile_form(data, false, interner)
+}
+
+const SPECIAL_FORMS: &'static [&'static str] = &[
+    "begin", "define", "defmacro", "define-syntax", "if", "let", "loop", "lambda", "recur", "quote", "quasiquote", "match",
+];
+
+fn head_name(list: &[Value], interner: &StringInterner) -> Option<String> {
+    list.first().and_then(Value::get_symbol).and_then(|id| interner.lookup(id)).map(String::from)
+}
+
+// recur is only valid in tail position
+fn compile_form(data: &Value, tail: bool, interner: &mut StringInterner) -> Result<Value, Value> {
+    let list = match data.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(data.clone()),
+    };
+
+    let head = head_name(&list, interner);
+    let len = list.len();
+    match head.as_ref().map(|x| &**x) {
+        Some("begin") => Ok(Value::new_begin(compile_body(&list[1..], tail, interner)?)),
+        Some("define") if len == 3 && list[1].get_symbol().is_some() => {
+            let expression = compile_form(&list[2], false, interner)?;
+            Ok(Value::new_define(list[1].get_symbol().unwrap(), expression))
+        },
+        Some("defmacro") if len > 3 && list[1].get_symbol().is_some() => {
+            let bindings = list_pattern(&list[2], interner)?;
+            Ok(Value::new_defmacro(list[1].get_symbol().unwrap(), bindings, compile_body(&list[3..], true, interner)?))
+        },
+        Some("define-syntax") if len == 3 && list[1].get_symbol().is_some() => {
+            match SyntaxRules::new(&list[2], interner) {
+                Some(rules) => Ok(Value::new_define_syntax(list[1].get_symbol().unwrap(), list[2].clone(), rules)),
+                None => Err(data.clone()),
+            }
+        },
+        Some("if") if len == 4 => {
+            let condition = compile_form(&list[1], false, interner)?;
+            let then = compile_form(&list[2], tail, interner)?;
+            Ok(Value::new_if(condition, then, compile_form(&list[3], tail, interner)?))
+        },
+        Some("let") if len > 2 => {
+            let bindings = binding_list(&list[1], interner)?;
+            Ok(Value::new_let(bindings, compile_body(&list[2..], tail, interner)?))
+        },
+        Some("loop") if len > 2 => {
+            let bindings = binding_list(&list[1], interner)?;
+            Ok(Value::new_loop(bindings, compile_body(&list[2..], true, interner)?))
+        },
+        // (lambda name? (args) body+)
+        Some("lambda") if len > 2 => {
+            let name = list[1].get_symbol().and_then(|id| interner.lookup(id)).map(String::from);
+            let start = if name.is_some() { 2 } else { 1 };
+            if len < start + 2 {
+                return Err(data.clone());
+            }
+            let bindings = list_pattern(&list[start], interner)?;
+            Ok(Value::new_lambda(name, bindings, compile_body(&list[start + 1..], true, interner)?))
+        },
+        Some("recur") if tail => Ok(Value::new_recur_form(compile_body(&list[1..], false, interner)?)),
+        Some("quote") if len == 2 => Ok(Value::new_quote(list[1].clone())),
+        Some("quasiquote") if len == 2 => Ok(Value::new_quasiquote(template(&list[1], 1, interner)?)),
+        Some("match") if len > 2 => {
+            let expression = compile_form(&list[1], false, interner)?;
+            let mut clauses = vec![];
+            for clause in &list[2..] {
+                clauses.push(match_clause(clause, tail, interner)?);
+            }
+            Ok(Value::new_match(expression, clauses))
+        },
+        // everything else is a call, but names of special forms can't be called
+        Some(name) if SPECIAL_FORMS.contains(&name) => Err(data.clone()),
+        _ => Ok(Value::new_list(&compile_body(&list, false, interner)?)),
+    }
+}
+
+// only the last form is in tail position, if the body is
+fn compile_body(code: &[Value], tail: bool, interner: &mut StringInterner) -> Result<Vec<Value>, Value> {
+    let mut compiled = vec![];
+    for (i, form) in code.iter().enumerate() {
+        compiled.push(compile_form(form, tail && i + 1 == code.len(), interner)?);
+    }
+    Ok(compiled)
+}
+
+fn pattern(data: &Value, interner: &mut StringInterner) -> Result<Pattern, Value> {
+    match data.get_symbol() {
+        Some(id) => Ok(Pattern::Symbol(id)),
+        None => list_pattern(data, interner),
+    }
+}
+
+fn list_pattern(data: &Value, interner: &mut StringInterner) -> Result<Pattern, Value> {
+    let list = data.get_list().ok_or(data.clone())?;
+    let mut patterns = vec![];
+    for x in &list {
+        patterns.push(pattern(x, interner)?);
+    }
+    let ampersand = Pattern::Symbol(interner.intern("&"));
+    Pattern::new_list(patterns, &ampersand).ok_or(data.clone())
+}
+
+// patterns of match, these can also contain wildcards, literals and predicates
+fn match_pattern(data: &Value, interner: &mut StringInterner) -> Result<Pattern, Value> {
+    if let Some(id) = data.get_symbol() {
+        return Ok(if interner.lookup(id) == Some("_") { Pattern::Wildcard } else { Pattern::Symbol(id) });
+    }
+
+    let list = match data.get_list() {
+        Some(list) => list,
+        None if data.get_bool().is_some() || data.get_char().is_some() || data.get_integer().is_some() || data.get_string().is_some() => {
+            return Ok(Pattern::Literal(data.clone()));
+        },
+        None => return Err(data.clone()),
+    };
+
+    match head_name(&list, interner).as_ref().map(|x| &**x) {
+        Some("quote") if list.len() == 2 => Ok(Pattern::Literal(list[1].clone())),
+        // (? predicate pattern?)
+        Some("?") => {
+            let predicate = match list.get(1).and_then(Value::get_symbol) {
+                Some(predicate) if list.len() <= 3 => predicate,
+                _ => return Err(data.clone()),
+            };
+            let pattern = match list.get(2) {
+                Some(pattern) => match_pattern(pattern, interner)?,
+                None => Pattern::Wildcard,
+            };
+            Ok(Pattern::Predicate(predicate, Box::new(pattern)))
+        },
+        _ => {
+            let mut patterns = vec![];
+            for x in &list {
+                patterns.push(match_pattern(x, interner)?);
+            }
+            let ampersand = Pattern::Symbol(interner.intern("&"));
+            Pattern::new_list(patterns, &ampersand).ok_or(data.clone())
+        },
+    }
+}
+
+// (pattern body+) or (pattern :when guard body+)
+fn match_clause(data: &Value, tail: bool, interner: &mut StringInterner) -> Result<MatchClause, Value> {
+    let list = match data.get_list() {
+        Some(ref list) if list.len() > 1 => list.clone(),
+        _ => return Err(data.clone()),
+    };
+    let pattern = match_pattern(&list[0], interner)?;
+    let code = compile_body(&list[1..], tail, interner)?;
+    MatchClause::with_guard(pattern, code, interner.intern(":when")).ok_or(data.clone())
+}
+
+// (pattern expr ..)
+fn binding_list(data: &Value, interner: &mut StringInterner) -> Result<Vec<(Pattern, Value)>, Value> {
+    let list = match data.get_list() {
+        Some(ref list) if list.len() % 2 == 0 => list.clone(),
+        _ => return Err(data.clone()),
+    };
+
+    let mut bindings = vec![];
+    for binding in list.chunks(2) {
+        bindings.push((pattern(&binding[0], interner)?, compile_form(&binding[1], false, interner)?));
+    }
+    Ok(bindings)
+}
+
+// the unquoted parts of a quasiquote are code, everything else is data
+// depth is the number of quasiquotes around data, only the holes of the outermost one are code,
+// in nested quasiquotes each unquote leaves one of them
+pub fn template(data: &Value, depth: usize, interner: &mut StringInterner) -> Result<QuasiTemplate, Value> {
+    let list = match data.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(QuasiTemplate::Datum(data.clone())),
+    };
+
+    let head = head_name(&list, interner);
+    let depth = match head.as_ref().map(|x| &**x) {
+        Some("unquote") if list.len() == 2 && depth == 1 => return Ok(QuasiTemplate::Unquote(compile_form(&list[1], false, interner)?)),
+        Some("unquote-splicing") if list.len() == 2 && depth == 1 => return Err(data.clone()),
+        Some("unquote") | Some("unquote-splicing") if list.len() == 2 => depth - 1,
+        Some("quasiquote") if list.len() == 2 => depth + 1,
+        _ => depth,
+    };
+
+    let mut templates = vec![];
+    for element in &list {
+        let element_list = element.get_list().unwrap_or(vec![]);
+        templates.push(match head_name(&element_list, interner).as_ref().map(|x| &**x) {
+            Some("unquote-splicing") if element_list.len() == 2 && depth == 1 => {
+                QuasiTemplate::UnquoteSplicing(compile_form(&element_list[1], false, interner)?)
+            },
+            _ => template(element, depth, interner)?,
+        });
+    }
+    Ok(QuasiTemplate::List(templates))
+}
@@ -0,0 +1,28 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    // the input isn't valid flip, the message points to the error
+    Parse(String),
+    // evaluation raised a condition with this message
+    Condition(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Error::Parse(ref msg) => write!(f, "{}", msg),
+            &Error::Condition(ref msg) => write!(f, "error: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match self {
+            &Error::Parse(_) => "parse error",
+            &Error::Condition(_) => "condition",
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use ::loader;
+use ::compiler;
+
+// Macros work on data, but the parser produces code with special forms. So a top-level form is turned
+// back into data, every tagged literal in it is replaced by what the reader function of its tag returns,
+// every macro call by what its macro returns and if anything changed, the result is compiled to code again.
+// The forms of a top-level begin or include are expanded and evaluated one at a time like top-level forms,
+// so a macro defined by one of them can be used in the following ones.
+pub fn evaluate_top_level(interpreter: &mut Interpreter, code: &Value) -> Value {
+    let data = code.to_data(&mut interpreter.interner);
+    match evaluate_form(interpreter, &data, Some(code)) {
+        Ok(res) => res,
+        Err(condition) => interpreter.raise(&condition),
+    }
+}
+
+// code is what the parser produced for data, if there is one
+fn evaluate_form(interpreter: &mut Interpreter, data: &Value, code: Option<&Value>) -> Result<Value, Value> {
+    let form = resolve_tags(interpreter, data, true).and_then(|form| macroexpand(interpreter, &form))?;
+    let list = form.get_list().unwrap_or(vec![]);
+    let head = list.first().and_then(Value::get_symbol).and_then(|id| interpreter.interner.lookup(id)).map(String::from);
+    match head.as_ref().map(|x| &**x) {
+        Some("begin") => {
+            let mut res = Value::empty_list();
+            for x in &list[1..] {
+                res = evaluate_form(interpreter, x, None)?;
+            }
+            Ok(res)
+        },
+        Some("include") => {
+            let mut res = Value::empty_list();
+            for_included(interpreter, &list, |interpreter, forms| {
+                for x in &forms {
+                    res = evaluate_form(interpreter, x, None)?;
+                }
+                Ok(())
+            })?;
+            Ok(res)
+        },
+        _ => {
+            let expanded = expand_all(interpreter, &form)?;
+            let code = match code {
+                Some(code) if &expanded == data => code.clone(),
+                _ => compiler::compile(&expanded, &mut interpreter.interner)
+                .map_err(|invalid| new_condition!(format!("macro expansion produced invalid code: {}", invalid.to_string(&interpreter.interner))))?,
+            };
+            Ok(interpreter.evaluate(&code))
+        },
+    }
+}
+
+// turns data, eg. returned by read, into code like the parser produces it, expanding all macros
+pub fn data_to_code(interpreter: &mut Interpreter, data: &Value) -> Result<Value, Value> {
+    let expanded = resolve_tags(interpreter, data, true).and_then(|data| expand_all(interpreter, &data))?;
+    compiler::compile(&expanded, &mut interpreter.interner)
+    .map_err(|invalid| new_condition!(format!("invalid code: {}", invalid.to_string(&interpreter.interner))))
+}
+
+// replaces all tagged literals in form, quoted or not, with the result of their reader function
+// the result is a literal, so in code it's quoted and is never evaluated
+fn resolve_tags(interpreter: &mut Interpreter, form: &Value, code: bool) -> Result<Value, Value> {
+    if let Some((tag, datum)) = form.get_tagged() {
+        let reader = match interpreter.reader_tags.get(&tag) {
+            Some(reader) => reader.clone(),
+            None => return Err(new_condition!(format!("no reader function for tag #{}", Value::new_symbol(tag).to_string(&interpreter.interner)))),
+        };
+        let datum = resolve_tags(interpreter, datum, false)?;
+        let resolved = interpreter.apply(&reader, &[datum]);
+        return Ok(if code { quote(interpreter, resolved) } else { resolved });
+    }
+
+    let list = match form.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(form.clone()),
+    };
+
+    let head = list[0].get_symbol().and_then(|id| interpreter.interner.lookup(id)).map(String::from);
+    let code = match head.as_ref().map(|x| &**x) {
+        Some("quote") | Some("define-syntax") if code => false,
+        Some("quasiquote") if code => return resolve_template(interpreter, form, 0),
+        _ => code,
+    };
+
+    let mut resolved = vec![];
+    for x in &list {
+        resolved.push(resolve_tags(interpreter, x, code)?);
+    }
+    Ok(Value::new_list(&resolved))
+}
+
+// only the holes of a quasiquote are code, depth is the number of quasiquotes around template
+fn resolve_template(interpreter: &mut Interpreter, template: &Value, depth: usize) -> Result<Value, Value> {
+    let list = match template.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return resolve_tags(interpreter, template, false),
+    };
+
+    let depth = match hole_depth(interpreter, &list, depth) {
+        Some(depth) => depth,
+        None => return resolve_tags(interpreter, template, true),
+    };
+    let mut resolved = vec![];
+    for x in &list {
+        resolved.push(resolve_template(interpreter, x, depth)?);
+    }
+    Ok(Value::new_list(&resolved))
+}
+
+// the depth of the elements of list, which is part of a quasiquote template with depth quasiquotes around it
+// None if list is a hole, i.e. code
+fn hole_depth(interpreter: &Interpreter, list: &[Value], depth: usize) -> Option<usize> {
+    let head = list[0].get_symbol().and_then(|id| interpreter.interner.lookup(id));
+    match head {
+        Some("unquote") | Some("unquote-splicing") if list.len() == 2 && depth == 1 => None,
+        Some("unquote") | Some("unquote-splicing") if list.len() == 2 => Some(depth - 1),
+        Some("quasiquote") if list.len() == 2 => Some(depth + 1),
+        _ => Some(depth),
+    }
+}
+
+fn quote(interpreter: &mut Interpreter, value: Value) -> Value {
+    Value::new_list(&[Value::new_symbol(interpreter.interner.intern("quote")), value])
+}
+
+// expands form once, if it is a macro call, returns None otherwise
+pub fn macroexpand_1(interpreter: &mut Interpreter, form: &Value) -> Result<Option<Value>, Value> {
+    let list = match form.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(None),
+    };
+
+    let mac = match list[0].get_symbol().and_then(|id| interpreter.lookup_symbol(id)) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    if let Some((rules, scope)) = mac.get_syntax_rules() {
+        let (expanded, renames) = rules.expand(form, &mut interpreter.interner)?;
+        // the symbols introduced by the template, which aren't bound by the expansion, refer to the bindings
+        // where the macro was defined, see Interpreter::lookup_symbol
+        for (fresh, original) in renames {
+            interpreter.renamed.insert(fresh, (original, scope.clone()));
+        }
+        return Ok(Some(expanded));
+    }
+    if mac.get_macro().is_none() {
+        return Ok(None);
+    }
+
+    // the macro gets the forms as they are
+    let expanded = mac.get_macro().unwrap().apply(interpreter, &list[1..]);
+    if expanded.get_condition().is_some() {
+        return Err(expanded);
+    }
+    Ok(Some(expanded))
+}
+
+// expands form until it isn't a macro call anymore
+pub fn macroexpand(interpreter: &mut Interpreter, form: &Value) -> Result<Value, Value> {
+    let mut form = form.clone();
+    while let Some(expanded) = macroexpand_1(interpreter, &form)? {
+        form = expanded;
+    }
+    Ok(form)
+}
+
+// expands all macro calls in form, except for those in quoted data and binding positions
+fn expand_all(interpreter: &mut Interpreter, form: &Value) -> Result<Value, Value> {
+    let form = macroexpand(interpreter, form)?;
+    let list = match form.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(form),
+    };
+
+    let head = list[0].get_symbol().and_then(|id| interpreter.interner.lookup(id)).map(String::from);
+    let expanded = match head.as_ref().map(|x| &**x) {
+        Some("quote") | Some("define-syntax") => return Ok(form),
+        Some("quasiquote") => return expand_holes(interpreter, &form, 0),
+        Some("include") => return include(interpreter, &list),
+        // (lambda name? args body+), (defmacro name args body+)
+        Some("lambda") | Some("defmacro") => {
+            let body_start = if list.len() > 1 && list[1].get_symbol().is_some() { 3 } else { 2 };
+            expand_from(interpreter, &list, body_start)?
+        },
+        // (let (pattern expr ..) body+), only the exprs are code
+        Some("let") | Some("loop") if list.len() > 1 => {
+            let mut expanded = expand_from(interpreter, &list, 2)?;
+            if let Some(bindings) = list[1].get_list() {
+                let mut new_bindings = vec![];
+                for (i, binding) in bindings.iter().enumerate() {
+                    new_bindings.push(if i % 2 == 1 { expand_all(interpreter, binding)? } else { binding.clone() });
+                }
+                expanded[1] = Value::new_list(&new_bindings);
+            }
+            expanded
+        },
+        // (match expr (pattern body+) ..), the patterns aren't code
+        Some("match") => {
+            let mut expanded = expand_from(interpreter, &list[..2.min(list.len())], 1)?;
+            for clause in &list[2..] {
+                expanded.push(match clause.get_list() {
+                    Some(ref clause) if clause.len() > 0 => Value::new_list(&expand_from(interpreter, clause, 1)?),
+                    _ => clause.clone(),
+                });
+            }
+            expanded
+        },
+        _ => expand_from(interpreter, &list, 0)?,
+    };
+    Ok(Value::new_list(&expanded))
+}
+
+// (include path+) is replaced by (begin form*) with the expanded forms of all files
+fn include(interpreter: &mut Interpreter, list: &[Value]) -> Result<Value, Value> {
+    let mut forms = vec![Value::new_symbol(interpreter.interner.intern("begin"))];
+    for_included(interpreter, list, |interpreter, included| {
+        for x in &included {
+            forms.push(expand_all(interpreter, x)?);
+        }
+        Ok(())
+    })?;
+    Ok(Value::new_list(&forms))
+}
+
+// calls f with the forms of each file of (include path+), while the file is on the load stack
+fn for_included<F>(interpreter: &mut Interpreter, list: &[Value], mut f: F) -> Result<(), Value>
+where F: FnMut(&mut Interpreter, Vec<Value>) -> Result<(), Value> {
+    for path in &list[1..] {
+        let path = match path.get_string() {
+            Some(path) => path.to_string(),
+            None => return Err(new_condition!(format!("include expected string, got: {}", path.to_string(&interpreter.interner)))),
+        };
+
+        // nested includes are relative to the included file
+        let path = loader::enter(interpreter, &path)?;
+        let res = loader::read_file(interpreter, &path).and_then(|forms| f(interpreter, forms));
+        interpreter.load_stack.pop();
+        res?;
+    }
+    Ok(())
+}
+
+// keeps the first n elements of list as they are and expands the rest
+fn expand_from(interpreter: &mut Interpreter, list: &[Value], n: usize) -> Result<Vec<Value>, Value> {
+    let mut expanded: Vec<Value> = list.iter().take(n).cloned().collect();
+    for form in list.iter().skip(n) {
+        expanded.push(expand_all(interpreter, form)?);
+    }
+    Ok(expanded)
+}
+
+// only the unquoted parts of a quasiquote are code
+fn expand_holes(interpreter: &mut Interpreter, template: &Value, depth: usize) -> Result<Value, Value> {
+    let list = match template.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return Ok(template.clone()),
+    };
+
+    match hole_depth(interpreter, &list, depth) {
+        None => Ok(Value::new_list(&expand_from(interpreter, &list, 1)?)),
+        Some(depth) => {
+            let mut expanded = vec![];
+            for element in &list {
+                expanded.push(expand_holes(interpreter, element, depth)?);
+            }
+            Ok(Value::new_list(&expanded))
+        },
+    }
+}
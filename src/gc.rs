@@ -0,0 +1,164 @@
+use std::cell::{Cell, Ref};
+use std::collections::HashMap;
+use std::rc::Rc;
+use ::value::Value;
+use ::scope::{self, ScopeData, ScopeNode};
+
+// Procs hold the scope they were created in, which holds the procs defined in it, so every
+// defined procedure is part of a cycle of Rcs. The collector finds scopes which are only
+// referenced by such cycles and clears their bindings, which frees the whole cycle.
+//
+// It counts the references between the scopes and the values reachable from them. A scope or
+// value with more references than that is used from outside, eg. by the interpreter or a rust
+// variable, so everything reachable from it is alive.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GcStats {
+    pub collections: usize,
+    // scopes freed by all collections
+    pub freed: usize,
+    // scopes which are currently alive
+    pub live: usize,
+}
+
+thread_local! {
+    static STATS: Cell<GcStats> = Cell::new(GcStats::default());
+    static THRESHOLD: Cell<usize> = Cell::new(MIN_THRESHOLD);
+}
+
+// collect automatically when there are more scopes than this
+const MIN_THRESHOLD: usize = 10000;
+
+enum Object<'a> {
+    // the bindings are None if they are borrowed right now
+    Scope(&'a ScopeNode, Option<&'a ScopeData>),
+    Value(&'a Value),
+}
+
+impl<'a> Object<'a> {
+    fn strong_count(&self) -> usize {
+        match self {
+            // one reference is held by the collector
+            &Object::Scope(node, _) => Rc::strong_count(node) - 1,
+            &Object::Value(value) => value.rc_strong_count(),
+        }
+    }
+}
+
+fn node_address(node: &ScopeNode) -> usize {
+    &**node as *const _ as usize
+}
+
+// the graph of all scopes and the values reachable from them, objects are identified by the address of their Rc
+struct Graph<'a> {
+    objects: Vec<Object<'a>>,
+    indices: HashMap<usize, usize>,
+    // indices of the objects each object references, an object can occur multiple times
+    references: Vec<Vec<usize>>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(nodes: &'a [ScopeNode], data: &'a [Option<Ref<ScopeData>>]) -> Self {
+        let mut graph = Graph { objects: vec![], indices: HashMap::new(), references: vec![] };
+        for (node, data) in nodes.iter().zip(data) {
+            graph.add(node_address(node), Object::Scope(node, data.as_ref().map(|data| &**data)));
+        }
+
+        let mut i = 0;
+        while i < graph.objects.len() {
+            let (values, scopes): (Vec<&'a Value>, Vec<&'a ScopeNode>) = match graph.objects[i] {
+                Object::Scope(node, data) => {
+                    // a scope which is in use might reference anything, but it isn't freed anyway
+                    let values = data.map(|data| data.values().collect()).unwrap_or(vec![]);
+                    (values, node.next().into_iter().collect())
+                },
+                Object::Value(value) => {
+                    let (values, scopes) = value.references();
+                    (values, scopes.into_iter().map(|scope| scope.head_node()).collect())
+                },
+            };
+
+            let mut references = vec![];
+            for value in values {
+                references.push(graph.add(value.rc_address(), Object::Value(value)));
+            }
+            for node in scopes {
+                // every node is registered, so it has already been added
+                references.push(graph.indices[&node_address(node)]);
+            }
+            graph.references.push(references);
+            i += 1;
+        }
+        graph
+    }
+
+    fn add(&mut self, address: usize, object: Object<'a>) -> usize {
+        if let Some(&i) = self.indices.get(&address) {
+            return i;
+        }
+        self.objects.push(object);
+        self.indices.insert(address, self.objects.len() - 1);
+        self.objects.len() - 1
+    }
+
+    // objects which are reachable from outside of the graph
+    fn alive(&self) -> Vec<bool> {
+        let mut internal = vec![0; self.objects.len()];
+        for references in &self.references {
+            for &i in references {
+                internal[i] += 1;
+            }
+        }
+
+        let mut alive = vec![false; self.objects.len()];
+        let mut stack: Vec<usize> = (0..self.objects.len()).filter(|&i| {
+            let borrowed = match self.objects[i] { Object::Scope(_, None) => true, _ => false };
+            borrowed || self.objects[i].strong_count() > internal[i]
+        }).collect();
+
+        while let Some(i) = stack.pop() {
+            if !alive[i] {
+                alive[i] = true;
+                stack.extend(self.references[i].iter().cloned().filter(|&j| !alive[j]));
+            }
+        }
+        alive
+    }
+}
+
+// frees unreachable scopes and returns their number
+pub fn collect() -> usize {
+    let nodes = scope::live_nodes();
+    let unreachable: Vec<&ScopeNode> = {
+        let data: Vec<Option<Ref<ScopeData>>> = nodes.iter().map(|node| node.elem().try_borrow().ok()).collect();
+        let alive = Graph::new(&nodes, &data).alive();
+        nodes.iter().enumerate().filter(|&(i, _)| !alive[i]).map(|(_, node)| node).collect()
+    };
+
+    // the bindings are dropped after all of them are taken, so no node is freed while it's still used here
+    let bindings: Vec<_> = unreachable.iter().map(|node| node.elem().borrow_mut().take_bindings()).collect();
+    let freed = unreachable.len();
+    drop(bindings);
+    drop(unreachable);
+    drop(nodes);
+
+    let live = scope::live_nodes().len();
+    THRESHOLD.with(|threshold| threshold.set(::std::cmp::max(MIN_THRESHOLD, 2 * live)));
+    STATS.with(|stats| {
+        let old = stats.get();
+        stats.set(GcStats { collections: old.collections + 1, freed: old.freed + freed, live: live });
+    });
+    freed
+}
+
+// collects only if a lot of scopes were created since the last collection
+pub fn collect_if_needed() {
+    if scope::tracked_nodes() > THRESHOLD.with(Cell::get) {
+        collect();
+    }
+}
+
+pub fn stats() -> GcStats {
+    let stats = STATS.with(Cell::get);
+    GcStats { live: scope::live_nodes().len(), ..stats }
+}
@@ -6,10 +6,13 @@ pub enum Error {
     // start..end
     InvalidEscape(usize, usize),
     InvalidToken(usize, usize),
+    RecurInNonTailPosition(usize, usize),
+    InvalidRestPattern(usize, usize),
+    InvalidPredicatePattern(usize, usize),
+    InvalidMatchClause(usize, usize),
+    InvalidSyntaxRules(usize, usize),
+    InvalidQuasiquote(usize, usize),
     // start
     UnexpectedEofString(usize),
     UnexpectedEofChar(usize),
-
-    // TODO: add more info
-    RecurInNonTailPosition,
 }
@@ -134,9 +134,52 @@ fn user_error(input: &str, err: &Error) -> String {
                 print_error_msg(&format!("invalid escape sequence")),
             ]
         },
-        &Error::RecurInNonTailPosition => {
+        &Error::InvalidRestPattern(start, end) => {
             vec![
-                print_error_msg(&format!("recur in non-tail position")),
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("invalid rest pattern\n")),
+                print_hint_msg("& has to be followed by exactly one pattern, like in (first & rest)")
+            ]
+        },
+        &Error::InvalidPredicatePattern(start, end) => {
+            vec![
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("invalid predicate pattern\n")),
+                print_hint_msg("predicate patterns look like (? predicate) or (? predicate pattern), where predicate is a symbol")
+            ]
+        },
+        &Error::InvalidMatchClause(start, end) => {
+            vec![
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("invalid match clause\n")),
+                print_hint_msg("a guarded clause looks like (pattern :when guard body+)")
+            ]
+        },
+        &Error::InvalidSyntaxRules(start, end) => {
+            // only mark the first line of multiline forms
+            let end = input[start..].find('\n').map(|i| start + i).map_or(end, |line_end| line_end.min(end));
+            vec![
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("invalid syntax rules\n")),
+                print_hint_msg("syntax rules look like (syntax-rules (literal*) ((_ pattern*) template)+), \
+                                where each list pattern contains at most one ..., which follows a pattern")
+            ]
+        },
+        &Error::InvalidQuasiquote(start, end) => {
+            vec![
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("invalid quasiquote\n")),
+                print_hint_msg("the holes of a nested quasiquote have to be code, ,@ is only allowed as an element of a list")
+            ]
+        },
+        &Error::RecurInNonTailPosition(start, end) => {
+            // only mark the first line of multiline forms
+            let end = input[start..].find('\n').map(|i| start + i).map_or(end, |line_end| line_end.min(end));
+            vec![
+                print_line_with_pos(input, start, end),
+                print_error_msg(&format!("recur in non-tail position\n")),
+                print_hint_msg("recur has to be the last thing evaluated in the body of a lambda or loop, \
+                                either directly or as the last expression of an if, let or begin in tail position")
             ]
         }
     };
@@ -22,21 +22,28 @@ pub enum Token<'input> {
     ClosingParen,
     Dot,
     QuoteTick,
+    Backquote,
+    Comma,
+    CommaAt,
     WhiteSpace,
     Char(char),
     Integer(i64),
     String(&'input str),
     Symbol(&'input str),
+    Tag(&'input str),
     True,
     False,
     Begin,
     Define,
+    Defmacro,
+    DefineSyntax,
     If,
     Let,
     Loop,
     Lambda,
     Recur,
     Quote,
+    Match,
 }
 
 // Tokenzer state
@@ -49,10 +56,13 @@ enum State<'input> {
     EatInteger(usize),
     WhiteSpace(usize),
     Minus(usize),
+    Dot(usize),
     Symbol(usize),
     StringStart(usize),
     StringBackslash(usize),
     Pound(usize),
+    Tag(usize),
+    Comma(usize),
     CharBegin(usize),
     EscapedChar(usize),
     FinishedChar(usize, char),
@@ -103,7 +113,9 @@ impl<'input> Iterator for Tokenizer<'input> {
                 (NewToken, pos, '(') => { self.next_char(); Finished((pos, Token::OpenParen, pos+1)) },
                 (NewToken, pos, ')') => { self.next_char(); Finished((pos, Token::ClosingParen, pos+1)) },
                 (NewToken, pos, '\'') => { self.next_char(); Finished((pos, Token::QuoteTick, pos+1)) },
-                (NewToken, pos, '.') => { self.next_char(); Finished((pos, Token::Dot, pos+1)) },
+                (NewToken, pos, '`') => { self.next_char(); Finished((pos, Token::Backquote, pos+1)) },
+                (NewToken, pos, ',') => Comma(pos),
+                (NewToken, pos, '.') => Dot(pos),
                 (NewToken, pos, '-') => Minus(pos),
                 (NewToken, pos, '"') => StringStart(pos),
                 (NewToken, pos, '#') => Pound(pos),
@@ -117,14 +129,27 @@ impl<'input> Iterator for Tokenizer<'input> {
                 (Minus(start), end, c) if end_of_item(c) => Finished((start, Token::Symbol(&self.text[start..end]), end)),
                 (Minus(start), _, _) => Symbol(start),
 
+                // a single dot, everything else starting with a dot is a symbol, eg. ...
+                (Dot(start), end, c) if end_of_item(c) => Finished((start, Token::Dot, end)),
+                (Dot(start), _, _) => Symbol(start),
+
                 // whitespace
                 (WhiteSpace(pos), _, c) if whitespace(c) => WhiteSpace(pos),
                 (WhiteSpace(start), end, _) => Finished((start, Token::WhiteSpace, end)),
 
+                // unquote and unquote-splicing
+                (Comma(start), _, '@') => { self.next_char(); Finished((start, Token::CommaAt, start+2)) },
+                (Comma(start), _, _) => Finished((start, Token::Comma, start+1)),
+
                 // chars
                 (Pound(pos), _, '\\') => CharBegin(pos),
                 (Pound(start), end, c) if end_of_item(c) => Finished((start, Token::Symbol(&self.text[start..end]), end)),
-                (Pound(pos), _, _) => Symbol(pos),
+                (Pound(pos), _, c) if c == '"' || c == '(' => Symbol(pos),
+                (Pound(pos), _, _) => Tag(pos),
+
+                // tags of tagged literals, eg. #inst, the name ends where the tagged form starts
+                (Tag(start), end, c) if end_of_item(c) || c == '"' || c == '(' => Finished((start, Token::Tag(&self.text[start+1..end]), end)),
+                (Tag(start), _, _) => Tag(start),
 
                 (CharBegin(pos), _, '\\') => EscapedChar(pos),
                 (CharBegin(_), _, EOF) => return Some(Err(Error::UnexpectedEofChar(self.text.len()))),
@@ -169,12 +194,15 @@ impl<'input> Iterator for Tokenizer<'input> {
                     let token = match &self.text[start..end] {
                         "begin" => Token::Begin,
                         "define" => Token::Define,
+                        "defmacro" => Token::Defmacro,
+                        "define-syntax" => Token::DefineSyntax,
                         "if" => Token::If,
                         "let" => Token::Let,
                         "loop" => Token::Loop,
                         "lambda" => Token::Lambda,
                         "recur" => Token::Recur,
                         "quote" => Token::Quote,
+                        "match" => Token::Match,
                         "true" => Token::True,
                         "false" => Token::False,
                         x => Token::Symbol(x),
@@ -244,6 +272,20 @@ fn printable_char(x: char) -> bool {
 }
 
 #[allow(dead_code)]
+pub fn escape_string(input: &str) -> String {
+    let mut s = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '\n' => s.push_str("\\n"),
+            '\t' => s.push_str("\\t"),
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            x => s.push(x),
+        }
+    }
+    s
+}
+
 pub fn unescape_string(input: &str) -> String {
     let mut chars = input.chars();
     let mut s = String::with_capacity(input.chars().count());
@@ -7,23 +7,92 @@ mod error;
 pub mod error_printing;
 
 use std::mem;
+use ::lalrpop_util::ParseError;
 use ::value::Value;
 use ::string_interner::StringInterner;
+use self::lexer::Token;
+use self::error::Error;
 
-pub use self::lexer::escape_char;
+pub use self::lexer::{escape_char, escape_string};
 
 static NO_INTERNER_ERROR_STRING: &'static str = "internal error: interner not set";
 
 pub fn parse<'input>(input: &'input str, interner: &mut StringInterner)
--> Result<Vec<Value>, ::lalrpop_util::ParseError<usize, lexer::Token<'input>, error::Error>> {
+-> Result<Vec<Value>, ParseError<usize, Token<'input>, Error>> {
     let tokenizer = lexer::Tokenizer::new(input);
     parser::parse_TopLevelItem(input, true, interner, tokenizer)
+    .map_err(|err| misplaced_recur(input, interner, err))
+}
+
+// reads input as data, i.e. without special forms, like quoted code
+pub fn read<'input>(input: &'input str, interner: &mut StringInterner)
+-> Result<Vec<Value>, ParseError<usize, Token<'input>, Error>> {
+    let tokenizer = lexer::Tokenizer::new(input);
+    parser::parse_Data(input, true, interner, tokenizer)
 }
 
 pub fn parse_integer<'input>(input: &'input str)
--> Result<Value, ::lalrpop_util::ParseError<usize, lexer::Token<'input>, error::Error>> {
+-> Result<Value, ParseError<usize, Token<'input>, Error>> {
     // safe because we give parse_Integer false, so it knows the pointer is invalid
     let fake_interner: &mut StringInterner = unsafe { mem::transmute(0usize) };
     let tokenizer = lexer::Tokenizer::new(input);
     parser::parse_Integer(input, false, fake_interner, tokenizer)
 }
+
+// The grammar only accepts recur in tail position, everywhere else it's just an unexpected token.
+// To report that properly the input is parsed again with recur as an ordinary symbol. If that works,
+// the misplaced recur was the only problem and it's the last one starting before the original error.
+fn misplaced_recur<'input>(input: &'input str, interner: &mut StringInterner, err: ParseError<usize, Token<'input>, Error>)
+-> ParseError<usize, Token<'input>, Error> {
+    let error_pos = match err {
+        ParseError::UnrecognizedToken { token: Some((pos, _, _)), .. } => pos,
+        ParseError::ExtraToken { token: (pos, _, _) } => pos,
+        _ => return err,
+    };
+
+    let recur_as_symbol = lexer::Tokenizer::new(input).map(|token| {
+        match token {
+            Ok((start, Token::Recur, end)) => Ok((start, Token::Symbol("recur"), end)),
+            x => x,
+        }
+    });
+
+    if parser::parse_TopLevelItem(input, true, interner, recur_as_symbol).is_err() {
+        return err;
+    }
+
+    match recur_forms(input).into_iter().filter(|&(start, _)| start <= error_pos).last() {
+        Some((start, end)) => ParseError::User { error: Error::RecurInNonTailPosition(start, end) },
+        None => err,
+    }
+}
+
+// spans of all (recur ..) forms in input, in order of appearance
+fn recur_forms(input: &str) -> Vec<(usize, usize)> {
+    let tokens: Vec<_> = lexer::Tokenizer::new(input)
+    .filter_map(Result::ok)
+    .filter(|&(_, token, _)| match token { Token::WhiteSpace => false, _ => true })
+    .collect();
+
+    let mut spans = vec![];
+    for (i, window) in tokens.windows(2).enumerate() {
+        if let (Token::OpenParen, Token::Recur) = (window[0].1, window[1].1) {
+            let start = window[0].0;
+            let mut end = input.len();
+            let mut depth = 0;
+            for &(_, token, right) in &tokens[i..] {
+                match token {
+                    Token::OpenParen => depth += 1,
+                    Token::ClosingParen => depth -= 1,
+                    _ => (),
+                }
+                if depth == 0 {
+                    end = right;
+                    break;
+                }
+            }
+            spans.push((start, end));
+        }
+    }
+    spans
+}
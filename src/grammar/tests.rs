@@ -16,6 +16,12 @@ fn whatever(x: &ParseError<usize, Token, Error>) -> usize {
         &ParseError::User{error: Error::UnexpectedEofString(_)} => EOF,
         &ParseError::User{error: Error::UnexpectedEofChar(_)} => EOF,
         &ParseError::User{error: Error::NonAsciiChar(x)} => x,
+        &ParseError::User{error: Error::RecurInNonTailPosition(start, _)} => start,
+        &ParseError::User{error: Error::InvalidRestPattern(start, _)} => start,
+        &ParseError::User{error: Error::InvalidPredicatePattern(start, _)} => start,
+        &ParseError::User{error: Error::InvalidMatchClause(start, _)} => start,
+        &ParseError::User{error: Error::InvalidSyntaxRules(start, _)} => start,
+        &ParseError::User{error: Error::InvalidQuasiquote(start, _)} => start,
         x => panic!("got: {:?}", x),
     }
 }
@@ -196,7 +202,7 @@ fn quote() {
     expect_ok!(parse, interner, "'(1 2)", quoted(Value::new_list(&[Value::new_integer(1), Value::new_integer(2)]), interner));
 
     // quoted lists should parse even if they contain invalid special forms
-    let special_forms = &["define", "quote", "if", "lambda", "let", "loop", "recur", "begin"];
+    let special_forms = &["define", "quote", "if", "lambda", "let", "loop", "recur", "begin", "match"];
     for &special in special_forms {
         let special_symbol = Value::new_symbol(interner.intern(special));
         expect_ok!(parse, interner, format!("'({})", special), quoted(Value::new_list(&[special_symbol.clone()]), interner));
@@ -263,6 +269,154 @@ fn tail_calls() {
     }
 }
 
+#[test]
+fn recur_in_non_tail_position() {
+    fn recur_span(input: &str) -> Option<(usize, usize)> {
+        match parse(input, &mut StringInterner::new()) {
+            Err(ParseError::User{error: Error::RecurInNonTailPosition(start, end)}) => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    assert_eq!(recur_span("(recur)"), Some((0, 7)));
+    assert_eq!(recur_span("(begin 1 (recur 1 2))"), Some((9, 20)));
+    assert_eq!(recur_span("(if (recur) 1 2)"), Some((4, 11)));
+    assert_eq!(recur_span("(loop () (recur) bla)"), Some((9, 16)));
+    assert_eq!(recur_span("(loop (x 1) (define y (recur 2)))"), Some((22, 31)));
+    assert_eq!(recur_span("(lambda (x) (recur (recur x)))"), Some((19, 28)));
+    assert_eq!(recur_span("(loop () (if true (recur) 1) 2)"), Some((18, 25)));
+
+    // other errors are still reported as usual
+    assert_eq!(recur_span("(loop () (recur)"), None);
+    assert_eq!(recur_span("(loop () (recur)) bla)"), None);
+    expect_error!(parse, "(loop () (recur) bla)", 9);
+}
+
+#[test]
+fn patterns() {
+    let interner = &mut StringInterner::new();
+    let valid = &["(let ((a b) x) a)", "(let ((a & b) x) a)", "(lambda ((a) & b) a)", "(loop (() x (a (b & c)) y) a)", "(let (& x) &)"];
+    for input in valid {
+        assert!(parse(input, interner).is_ok(), "expected {:?} to parse", input);
+    }
+
+    expect_error!(parse, "(let ((a &) x) a)", 6);
+    expect_error!(parse, "(lambda (a & b c) a)", 8);
+    expect_error!(parse, "(let ((a (& b c)) x) a)", 9);
+}
+
+#[test]
+fn match_form() {
+    let interner = &mut StringInterner::new();
+    let valid = &[
+        "(match x (1 'one) ('sym 2) (\"s\" 3) (#\\c 4) (true 5) (() 6) (_ 7))",
+        "(match x ((a & b) a) ((? integer?) 1) ((? list? (a)) a) (n :when (< n 1) n))",
+        "(loop () (match x (1 (recur)) (_ 2)))",
+        "(loop () (match x (1 2) (n :when (< n 1) 3 (recur)) (_ 4)))",
+        "(lambda (x) (match x (1 (if true (recur 2) 1))))",
+    ];
+    for input in valid {
+        assert!(parse(input, interner).is_ok(), "expected {:?} to parse", input);
+    }
+
+    expect_error!(parse, "(match x)");
+    expect_error!(parse, "(match x ((? 1) 1))", 10);
+    expect_error!(parse, "(match x ((? a b c) 1))", 10);
+    expect_error!(parse, "(match x (a :when))", 9);
+    expect_error!(parse, "(match x (a :when true))", 9);
+    expect_error!(parse, "(match x (1 (recur)))", 12);
+    expect_error!(parse, "(loop () (match x (a :when (recur) 1)))", 27);
+    expect_error!(parse, "(loop () (match x (1 (recur) 2)))", 21);
+}
+
+#[test]
+fn quasiquote() {
+    let interner = &mut StringInterner::new();
+    let valid = &[
+        "`a",
+        "`(a b)",
+        "`(a ,b ,@c)",
+        "`(,(+ 1 2) (nested ,@(list 1 2)) 'x)",
+        "`,a",
+        "'(a `b ,c ,@d)",
+        "`(a `(b ,(c ,x)))",
+        "``,,@x",
+    ];
+    for input in valid {
+        assert!(parse(input, interner).is_ok(), "expected {:?} to parse", input);
+    }
+
+    expect_error!(parse, ",a", 0);
+    expect_error!(parse, ",@a", 0);
+    expect_error!(parse, "(list ,a)", 6);
+    expect_error!(parse, "`,@a", 1);
+    expect_error!(parse, "`(a , b)", 5);
+    expect_error!(parse, "`(a `(b ,,(if 1)))", 4);
+    expect_error!(parse, "`(a `(b ,,(recur)))", 4);
+}
+
+#[test]
+fn special_forms_round_trip() {
+    let interner = &mut StringInterner::new();
+    let forms = &[
+        "(begin 1 2)",
+        "(define a (quote b))",
+        "(defmacro m (a & b) `(list ,a ,@b))",
+        "(if true 1 2)",
+        "(lambda f ((a b) & c) (recur 1 2 3))",
+        "(let (a 1 (b c) (list 2 3)) (+ a b c))",
+        "(loop (a 1) (if (= a 0) a (recur (- a 1))))",
+        "(loop () (begin 1 (recur)))",
+        "(match x (1 (quote one)) ((? integer? n) :when (< n 2) n) ('(a b) \"s\") (_ #\\n))",
+        "`(a ,(b c) ,@d (quote e))",
+        "(define-syntax m (syntax-rules () ((_ a ...) (list a ...))))",
+    ];
+    for &form in forms {
+        let parsed = parse(form, interner).unwrap();
+        assert_eq!(parsed[0].to_string(interner), form);
+    }
+}
+
+#[test]
+fn define_syntax() {
+    let interner = &mut StringInterner::new();
+    let valid = &[
+        "(define-syntax m (syntax-rules () ((_) 1)))",
+        "(define-syntax m (syntax-rules (=>) ((_ a => b ...) (list a b ...)) ((m) '())))",
+        "'(a ...)",
+    ];
+    for input in valid {
+        assert!(parse(input, interner).is_ok(), "expected {:?} to parse", input);
+    }
+
+    expect_error!(parse, "(define-syntax m 1)", 0);
+    expect_error!(parse, "(define-syntax m (syntax-rules ()))", 0);
+    expect_error!(parse, "(define-syntax m (syntax-rules (1) ((_) 1)))", 0);
+    expect_error!(parse, "(define-syntax m (syntax-rules () (_ 1)))", 0);
+    expect_error!(parse, "(define-syntax m (syntax-rules () ((_ ... a) 1)))", 0);
+    expect_error!(parse, "(define-syntax m (syntax-rules () ((_ a ... b ...) 1)))", 0);
+}
+
+#[test]
+fn tagged_literals() {
+    let interner = &mut StringInterner::new();
+    let valid = &[
+        "#inst \"2017-01-01\"",
+        "#re\"a+\"",
+        "#point(1 2)",
+        "#a #b c",
+        "'(x #tag (y z))",
+        "(f #tag y)",
+    ];
+    for input in valid {
+        assert!(parse(input, interner).is_ok(), "expected {:?} to parse", input);
+    }
+    assert_eq!(parse("#point (1 2)", interner).unwrap()[0].to_string(interner), "#point (1 2)");
+
+    expect_error!(parse, "#tag");
+    expect_error!(parse, "(#tag)", 5);
+}
+
 // TODO add tests for special forms
 
 #[test]
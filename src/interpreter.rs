@@ -1,72 +1,318 @@
-use ::value::Value;
+use std::collections::HashMap;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::time::Duration;
+use ::value::{Value, Condition, NativeProc, Arity, IntoNative, IntoArgs};
 use ::scope::Scope;
 use ::native;
+use ::expander;
+use ::grammar::{self, error_printing};
+use ::loader;
+use ::limits::Limits;
+use ::gc::{self, GcStats};
+use ::error::Error;
 use ::string_interner::StringInterner;
+use ::port::Port;
+use ::module::{self, Module};
 
+// The fields are internal, embedders use the methods below.
+// Conditions unwind the rust stack up to catch_conditions, so the host has to be built with panic = "unwind",
+// which is the default, building with panic = "abort" fails, see lib.rs.
 pub struct Interpreter {
-    pub interner: StringInterner,
-    pub current_scope: Scope,
+    pub(crate) interner: StringInterner,
+    pub(crate) current_scope: Scope,
+    // scope of the natives, the parent of the global scope and of every module scope
+    pub(crate) builtins: Scope,
+    pub(crate) modules: HashMap<u64, Module>,
+    // number of lambda or loop bodies currently being evaluated, recur is only valid inside of them
+    pub(crate) recursion_points: usize,
+    // reader functions of tagged literals by tag
+    pub(crate) reader_tags: HashMap<u64, Value>,
+    // ports used by the io procedures, if they aren't passed one explicitly
+    pub(crate) current_input: Value,
+    pub(crate) current_output: Value,
+    pub(crate) current_error: Value,
+    // canonical paths of the files currently being loaded or included, the last one is the innermost
+    pub(crate) load_stack: Vec<PathBuf>,
+    // fuel, deadline, max size of values and max depth for evaluate
+    limits: Limits,
+    // number of evaluations in progress
+    depth: usize,
+    // fixed when the interpreter is created, procedures needing others aren't bound
+    capabilities: Vec<Capability>,
+    // symbols introduced by the templates of syntax rules, with their original symbol and the scope of the macro
+    pub(crate) renamed: HashMap<u64, (u64, Scope)>,
+    // forms like module, which get their args unevaluated and can't be used as values
+    syntax: HashMap<u64, fn(&mut Interpreter, &[Value]) -> Value>,
 }
 
+// access to the host which can be granted to flip code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    // open-input-file, open-output-file, load, include and importing modules from files
+    FileSystem,
+    // stdin, stdout and stderr as current ports, without it they are string ports
+    Console,
+}
+
+pub const ALL_CAPABILITIES: &'static [Capability] = &[Capability::FileSystem, Capability::Console];
+
+// max_depth of sandboxed, small enough for the 2 MiB stack of a spawned thread, even in debug builds
+pub const SANDBOX_MAX_DEPTH: usize = 500;
+// max_size and fuel of sandboxed, the fuel isn't refilled, embedders can set more with limits_mut
+pub const SANDBOX_MAX_SIZE: usize = 1_000_000;
+pub const SANDBOX_FUEL: u64 = 100_000_000;
+
+// standard procedures written in flip, in the order they are evaluated
+const PRELUDE: &'static [(&'static str, &'static str)] = &[
+    ("core.flip", include_str!("prelude/core.flip")),
+    ("numbers.flip", include_str!("prelude/numbers.flip")),
+    ("lists.flip", include_str!("prelude/lists.flip")),
+];
+
 impl Interpreter {
     pub fn new() -> Self {
+        Interpreter::create(true, ALL_CAPABILITIES)
+    }
+
+    // only the natives are defined, eg. for embedding
+    pub fn without_prelude() -> Self {
+        Interpreter::create(false, ALL_CAPABILITIES)
+    }
+
+    // For untrusted code: nothing outside of the interpreter can be accessed.
+    // Output goes to a string port, which can be read with get_output_string.
+    // Recursion, the size of values and the number of evaluation steps are limited, see the SANDBOX_ constants.
+    pub fn sandboxed() -> Self {
+        let mut interpreter = Interpreter::create(true, &[]);
+        interpreter.limits.max_depth = Some(SANDBOX_MAX_DEPTH);
+        interpreter.limits.max_size = Some(SANDBOX_MAX_SIZE);
+        interpreter.limits.fuel = Some(SANDBOX_FUEL);
+        interpreter
+    }
+
+    // only the given capabilities are granted, eg. &[Capability::Console]
+    pub fn with_capabilities(capabilities: &[Capability]) -> Self {
+        Interpreter::create(true, capabilities)
+    }
+
+    fn create(prelude: bool, capabilities: &[Capability]) -> Self {
+        let console = capabilities.contains(&Capability::Console);
+        let builtins = Scope::new();
         let mut interpreter = Interpreter {
             interner: StringInterner::new(),
-            current_scope: Scope::new(),
+            current_scope: builtins.clone(),
+            builtins: builtins,
+            modules: HashMap::new(),
+            recursion_points: 0,
+            reader_tags: HashMap::new(),
+            current_input: Value::new_port(if console { Port::stdin() } else { Port::input_string("") }),
+            current_output: Value::new_port(if console { Port::stdout() } else { Port::output_string() }),
+            current_error: Value::new_port(if console { Port::stderr() } else { Port::output_string() }),
+            load_stack: vec![],
+            limits: Limits::default(),
+            depth: 0,
+            capabilities: capabilities.to_vec(),
+            renamed: HashMap::new(),
+            syntax: HashMap::new(),
         };
         interpreter.init();
+        if prelude {
+            interpreter.load_prelude();
+        }
+        interpreter.current_scope = interpreter.builtins.new_child();
         interpreter
     }
 
     fn init(&mut self) {
-        self.add_str_to_current_scope("eq?", Value::new_native_proc(native::poly_eq));
-
-        self.add_str_to_current_scope("null?", Value::new_native_proc(native::null_));
-        self.add_str_to_current_scope("boolean?", Value::new_native_proc(native::boolean_));
-        self.add_str_to_current_scope("symbol?", Value::new_native_proc(native::symbol_));
-        self.add_str_to_current_scope("integer?", Value::new_native_proc(native::integer_));
-        self.add_str_to_current_scope("char?", Value::new_native_proc(native::char_));
-        self.add_str_to_current_scope("string?", Value::new_native_proc(native::string_));
-        self.add_str_to_current_scope("procedure?", Value::new_native_proc(native::procedure_));
-        self.add_str_to_current_scope("list?", Value::new_native_proc(native::list_));
-
-        self.add_str_to_current_scope("char->integer", Value::new_native_proc(native::char_integer));
-        self.add_str_to_current_scope("integer->char", Value::new_native_proc(native::integer_char));
-        self.add_str_to_current_scope("number->string", Value::new_native_proc(native::number_string));
-        self.add_str_to_current_scope("string->number", Value::new_native_proc(native::string_number));
-        self.add_str_to_current_scope("symbol->string", Value::new_native_proc(native::symbol_string));
-        self.add_str_to_current_scope("string->symbol", Value::new_native_proc(native::string_symbol));
-
-        self.add_str_to_current_scope("+", Value::new_native_proc(native::plus));
-        self.add_str_to_current_scope("-", Value::new_native_proc(native::minus));
-        self.add_str_to_current_scope("*", Value::new_native_proc(native::multiply));
-        self.add_str_to_current_scope("quotient", Value::new_native_proc(native::quotient));
-        self.add_str_to_current_scope("remainder", Value::new_native_proc(native::remainder));
-
-        self.add_str_to_current_scope("=", Value::new_native_proc(native::eq));
-        self.add_str_to_current_scope(">", Value::new_native_proc(native::gt));
-        self.add_str_to_current_scope(">=", Value::new_native_proc(native::ge));
-        self.add_str_to_current_scope("<", Value::new_native_proc(native::lt));
-        self.add_str_to_current_scope("<=", Value::new_native_proc(native::le));
-
-        self.add_str_to_current_scope("list", Value::new_native_proc(native::list));
-        self.add_str_to_current_scope("first", Value::new_native_proc(native::first));
-        self.add_str_to_current_scope("rest", Value::new_native_proc(native::rest));
-
-        self.add_str_to_current_scope("symbol-space", Value::new_native_proc(native::symbol_space));
+        self.add_builtin("eq?", Arity::AtLeast(2), native::poly_eq);
+
+        self.add_builtin("null?", Arity::Exact(1), native::null_);
+        self.add_builtin("boolean?", Arity::Exact(1), native::boolean_);
+        self.add_builtin("symbol?", Arity::Exact(1), native::symbol_);
+        self.add_builtin("integer?", Arity::Exact(1), native::integer_);
+        self.add_builtin("char?", Arity::Exact(1), native::char_);
+        self.add_builtin("string?", Arity::Exact(1), native::string_);
+        self.add_builtin("procedure?", Arity::Exact(1), native::procedure_);
+        self.add_builtin("list?", Arity::Exact(1), native::list_);
+
+        self.add_builtin("char->integer", Arity::Exact(1), native::char_integer);
+        self.add_builtin("integer->char", Arity::Exact(1), native::integer_char);
+        self.add_builtin("number->string", Arity::Exact(1), native::number_string);
+        self.add_builtin("string->number", Arity::Exact(1), native::string_number);
+        self.add_builtin("symbol->string", Arity::Exact(1), native::symbol_string);
+        self.add_builtin("string->symbol", Arity::Exact(1), native::string_symbol);
+
+        self.add_builtin("+", Arity::AtLeast(0), native::plus);
+        self.add_builtin("-", Arity::AtLeast(0), native::minus);
+        self.add_builtin("*", Arity::AtLeast(0), native::multiply);
+        self.add_builtin("quotient", Arity::Exact(2), native::quotient);
+        self.add_builtin("remainder", Arity::Exact(2), native::remainder);
+
+        self.add_builtin("=", Arity::AtLeast(2), native::eq);
+        self.add_builtin(">", Arity::AtLeast(2), native::gt);
+        self.add_builtin(">=", Arity::AtLeast(2), native::ge);
+        self.add_builtin("<", Arity::AtLeast(2), native::lt);
+        self.add_builtin("<=", Arity::AtLeast(2), native::le);
+
+        self.add_builtin("list", Arity::AtLeast(0), native::list);
+        self.add_builtin("first", Arity::Exact(1), native::first);
+        self.add_builtin("rest", Arity::Exact(1), native::rest);
+        self.add_builtin("cons", Arity::Exact(2), native::cons);
+        self.add_builtin("apply", Arity::AtLeast(2), native::apply);
+        self.add_builtin("map", Arity::AtLeast(2), native::map);
+        self.add_builtin("filter", Arity::Exact(2), native::filter);
+        self.add_builtin("fold", Arity::Exact(3), native::fold);
+        self.add_builtin("reduce", Arity::Exact(2), native::reduce);
+        self.add_builtin("append", Arity::AtLeast(0), native::append);
+        self.add_builtin("reverse", Arity::Exact(1), native::reverse);
+        self.add_builtin("length", Arity::Exact(1), native::length);
+        self.add_builtin("nth", Arity::Exact(2), native::nth);
+        self.add_builtin("take", Arity::Exact(2), native::take);
+        self.add_builtin("drop", Arity::Exact(2), native::drop);
+        self.add_builtin("range", Arity::Range(1, 2), native::range);
+        self.add_builtin("sort", Arity::Range(1, 2), native::sort);
+
+        self.add_builtin("string-length", Arity::Exact(1), native::string_length);
+        self.add_builtin("string-append", Arity::AtLeast(0), native::string_append);
+        self.add_builtin("substring", Arity::Range(2, 3), native::substring);
+        self.add_builtin("string-ref", Arity::Exact(2), native::string_ref);
+        self.add_builtin("string-split", Arity::Exact(2), native::string_split);
+        self.add_builtin("string-join", Arity::Range(1, 2), native::string_join);
+        self.add_builtin("string-index", Arity::Exact(2), native::string_index);
+        self.add_builtin("string-contains?", Arity::Exact(2), native::string_contains);
+        self.add_builtin("string-upcase", Arity::Exact(1), native::string_upcase);
+        self.add_builtin("string-downcase", Arity::Exact(1), native::string_downcase);
+        self.add_builtin("string-trim", Arity::Exact(1), native::string_trim);
+        self.add_builtin("string->list", Arity::Exact(1), native::string_list);
+        self.add_builtin("list->string", Arity::Exact(1), native::list_string);
+
+        self.add_builtin("string=?", Arity::AtLeast(2), native::string_eq);
+        self.add_builtin("string<?", Arity::AtLeast(2), native::string_lt);
+        self.add_builtin("string<=?", Arity::AtLeast(2), native::string_le);
+        self.add_builtin("string>?", Arity::AtLeast(2), native::string_gt);
+        self.add_builtin("string>=?", Arity::AtLeast(2), native::string_ge);
+
+        self.add_builtin("char-alphabetic?", Arity::Exact(1), native::char_alphabetic);
+        self.add_builtin("char-numeric?", Arity::Exact(1), native::char_numeric);
+        self.add_builtin("char-whitespace?", Arity::Exact(1), native::char_whitespace);
+        self.add_builtin("char-upper-case?", Arity::Exact(1), native::char_upper_case);
+        self.add_builtin("char-lower-case?", Arity::Exact(1), native::char_lower_case);
+        self.add_builtin("char-upcase", Arity::Exact(1), native::char_upcase);
+        self.add_builtin("char-downcase", Arity::Exact(1), native::char_downcase);
+        self.add_builtin("digit-value", Arity::Exact(1), native::digit_value);
+
+        self.add_builtin("char=?", Arity::AtLeast(2), native::char_eq);
+        self.add_builtin("char<?", Arity::AtLeast(2), native::char_lt);
+        self.add_builtin("char<=?", Arity::AtLeast(2), native::char_le);
+        self.add_builtin("char>?", Arity::AtLeast(2), native::char_gt);
+        self.add_builtin("char>=?", Arity::AtLeast(2), native::char_ge);
+
+        if self.has_capability(Capability::FileSystem) {
+            self.add_builtin("open-input-file", Arity::Exact(1), native::open_input_file);
+            self.add_builtin("open-output-file", Arity::Exact(1), native::open_output_file);
+            self.add_builtin("load", Arity::Exact(1), native::load);
+        }
+        self.add_builtin("open-input-string", Arity::Exact(1), native::open_input_string);
+        self.add_builtin("open-output-string", Arity::Exact(0), native::open_output_string);
+        self.add_builtin("get-output-string", Arity::Exact(1), native::get_output_string);
+        self.add_builtin("current-input-port", Arity::Exact(0), native::current_input_port);
+        self.add_builtin("current-output-port", Arity::Exact(0), native::current_output_port);
+        self.add_builtin("current-error-port", Arity::Exact(0), native::current_error_port);
+        self.add_builtin("input-port?", Arity::Exact(1), native::input_port);
+        self.add_builtin("output-port?", Arity::Exact(1), native::output_port);
+        self.add_builtin("eof-object?", Arity::Exact(1), native::eof_object);
+        self.add_builtin("read-char", Arity::Range(0, 1), native::read_char);
+        self.add_builtin("peek-char", Arity::Range(0, 1), native::peek_char);
+        self.add_builtin("read-line", Arity::Range(0, 1), native::read_line);
+        self.add_builtin("write-string", Arity::Range(1, 2), native::write_string);
+        self.add_builtin("close-port", Arity::Exact(1), native::close_port);
+        self.add_builtin("with-output-to-string", Arity::Exact(1), native::with_output_to_string);
+
+        self.add_builtin("display", Arity::Range(1, 2), native::display);
+        self.add_builtin("write", Arity::Range(1, 2), native::write);
+        self.add_builtin("newline", Arity::Range(0, 1), native::newline);
+        self.add_builtin("println", Arity::AtLeast(0), native::println);
+
+        self.add_builtin("read", Arity::Range(0, 1), native::read);
+        self.add_builtin("read-all", Arity::Range(0, 1), native::read_all);
+
+        self.add_syntax("module", native::module);
+        self.add_syntax("import", native::import);
+
+        self.add_builtin("eval", Arity::Range(1, 2), native::eval);
+        self.add_builtin("current-environment", Arity::Exact(0), native::current_environment);
+
+        self.add_builtin("macroexpand-1", Arity::Exact(1), native::macroexpand_1);
+        self.add_builtin("macroexpand", Arity::Exact(1), native::macroexpand);
+
+        self.add_builtin("register-tag", Arity::Exact(2), native::register_tag);
+
+        self.add_builtin("symbol-space", Arity::Exact(0), native::symbol_space);
+
+        self.add_builtin("gc", Arity::Exact(0), native::gc);
+        self.add_builtin("gc-stats", Arity::Exact(0), native::gc_stats);
+    }
+
+    // evaluated in the builtins scope, so modules can use the prelude too
+    fn load_prelude(&mut self) {
+        for &(name, source) in PRELUDE {
+            let code = grammar::parse(source, &mut self.interner).unwrap_or_else(|_| panic!("internal error: prelude {} doesn't parse", name));
+            for x in &code {
+                self.evaluate_top_level(x);
+            }
+        }
+    }
+
+    // top-level forms have their macros expanded before they are evaluated
+    pub fn evaluate_top_level(&mut self, value: &Value) -> Value {
+        expander::evaluate_top_level(self, value)
     }
 
     pub fn evaluate(&mut self, value: &Value) -> Value {
+        let res = match self.limits.step().and_then(|()| self.limits.check_depth(self.depth)) {
+            Ok(()) => {
+                self.depth += 1;
+                let res = self.evaluate_step(value);
+                self.depth -= 1;
+                res
+            },
+            Err(msg) => new_condition!(msg),
+        };
+        let res = match self.limits.check_size(&res) {
+            Ok(()) => res,
+            Err(msg) => new_condition!(msg),
+        };
+
+        if res.get_condition().is_some() {
+            self.raise(&res);
+        }
+        res
+    }
+
+    // conditions unwind the stack up to catch_conditions, without the noise of the panic hook
+    pub(crate) fn raise(&self, condition: &Value) -> ! {
+        let msg = condition.get_condition().unwrap_or(condition).to_display_string(&self.interner);
+        panic::resume_unwind(Box::new(msg))
+    }
+
+    fn evaluate_step(&mut self, value: &Value) -> Value {
         let res: Value;
-        if let Some(mut list) = value.get_list() {
-            if list.len() > 0 {
-                let (func, mut args) = list.split_at_mut(1);
+        if let Some(list) = value.get_list() {
+            // syntax like module can be shadowed by local bindings of the same name
+            let syntax = list.first().and_then(Value::get_symbol)
+                .and_then(|id| self.syntax.get(&id).cloned().filter(|_| self.current_scope.lookup_symbol(id).is_none()));
+            if let Some(f) = syntax {
+                res = f(self, &list[1..]);
+            } else if list.len() > 0 {
+                let (func, args) = list.split_at(1);
                 let func = self.evaluate(&func[0]);
 
-                if let Some(f) = func.get_native_fn_ptr() {
-                    res = f(self, &mut args)
+                if let Some(native) = func.get_native_proc() {
+                    res = native.evaluate(self, args)
                 } else if let Some(p) = func.get_proc() {
-                    res = p.evaluate(self, &args);
+                    res = p.evaluate(self, args);
                 } else {
                     res = Value::new_condition(Value::new_string(format!("tried to call {}, which is not possible", func.to_string(&self.interner))));
                 }
@@ -76,23 +322,254 @@ impl Interpreter {
         } else if let Some(special_form) = value.get_special_form() {
             res = special_form.evaluate(self);
         } else if let Some(symbol) = value.get_symbol() {
-            res = self.current_scope
-            .lookup_symbol(symbol)
+            res = self.lookup_symbol(symbol)
             .unwrap_or(Value::new_condition(Value::new_string(format!("undefined ident: {}", value.to_string(&self.interner)))));
         } else {
             res = value.clone();
         }
+        res
+    }
+
+    // parses and evaluates all top-level forms of input, returns the result of the last one
+    // a condition is returned as Err, catching it needs panic = "unwind"
+    pub fn eval_str(&mut self, input: &str) -> Result<Value, Error> {
+        if input.trim().len() == 0 {
+            return Ok(Value::empty_list());
+        }
+        let code = grammar::parse(input, &mut self.interner)
+        .map_err(|err| Error::Parse(error_printing::create_error_message(input, &err)))?;
+
+        self.catch_conditions(|interpreter| {
+            let mut res = Value::empty_list();
+            for x in &code {
+                res = interpreter.evaluate_top_level(x);
+            }
+            res
+        })
+    }
+
+    // evaluates the file like the load procedure
+    pub fn load_file(&mut self, path: &str) -> Result<Value, Error> {
+        self.catch_conditions(|interpreter| loader::load(interpreter, path))
+    }
+
+    // Runs f and turns a condition raised by it into an error. The state f might have left behind,
+    // eg. the scope of a procedure whose body raised the condition, is reset.
+    pub fn catch_conditions<F: FnOnce(&mut Interpreter) -> Value>(&mut self, f: F) -> Result<Value, Error> {
+        let scope = self.current_scope.clone();
+        let recursion_points = self.recursion_points;
+        let depth = self.depth;
+        let load_stack = self.load_stack.clone();
+        let ports = (self.current_input.clone(), self.current_output.clone(), self.current_error.clone());
+        if depth == 0 {
+            self.limits.start();
+        }
+
+        let caught = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        // procedures defined by f might have left cycles behind
+        gc::collect_if_needed();
+
+        let res = match caught {
+            Ok(ref res) if res.get_condition().is_some() => Err(res.get_condition().unwrap().to_display_string(&self.interner)),
+            Ok(res) => return Ok(res),
+            Err(payload) => match payload.downcast::<String>() {
+                Ok(msg) => Err(*msg),
+                // a real panic
+                Err(payload) => panic::resume_unwind(payload),
+            },
+        };
+
+        self.current_scope = scope;
+        self.recursion_points = recursion_points;
+        self.depth = depth;
+        self.load_stack = load_stack;
+        let (input, output, error) = ports;
+        self.current_input = input;
+        self.current_output = output;
+        self.current_error = error;
+        res.map_err(Error::Condition)
+    }
+
+    // eg. to print values with Value::to_string
+    pub fn interner(&self) -> &StringInterner {
+        &self.interner
+    }
+
+    // eg. to create symbols
+    pub fn interner_mut(&mut self) -> &mut StringInterner {
+        &mut self.interner
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+
+    // the ports used by the io procedures, if they aren't passed one explicitly
+    pub fn current_input(&self) -> &Value {
+        &self.current_input
+    }
+
+    pub fn current_output(&self) -> &Value {
+        &self.current_output
+    }
+
+    pub fn current_error(&self) -> &Value {
+        &self.current_error
+    }
+
+    pub fn set_current_input(&mut self, port: Port) {
+        self.current_input = Value::new_port(port);
+    }
+
+    pub fn set_current_output(&mut self, port: Port) {
+        self.current_output = Value::new_port(port);
+    }
+
+    pub fn set_current_error(&mut self, port: Port) {
+        self.current_error = Value::new_port(port);
+    }
+
+    // every following eval_str, load_file and call raises a condition after it took longer than timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.limits.timeout = Some(timeout);
+    }
+
+    // natives check the size of the lists and strings they create before allocating them
+    pub(crate) fn check_list_len(&self, len: usize) -> Result<(), Value> {
+        self.limits.check_list_len(len).map_err(|msg| new_condition!(msg))
+    }
+
+    pub(crate) fn check_string_len(&self, len: usize) -> Result<(), Value> {
+        self.limits.check_string_len(len).map_err(|msg| new_condition!(msg))
+    }
+
+    // frees procedures and scopes which are only referenced by each other, returns the number of freed scopes
+    pub fn collect_garbage(&mut self) -> usize {
+        gc::collect()
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        gc::stats()
+    }
+
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    // symbols which aren't bound in the current scope can refer to exports of modules, like str/join
+    pub fn lookup_symbol(&mut self, id: u64) -> Option<Value> {
+        self.current_scope.lookup_symbol(id)
+        .or_else(|| module::lookup_qualified(self, id))
+        .or_else(|| self.lookup_renamed(id))
+    }
+
+    // a symbol introduced by syntax rules, which isn't bound where it is used, refers to the binding of
+    // its original symbol where the macro was defined
+    fn lookup_renamed(&mut self, id: u64) -> Option<Value> {
+        let (original, scope) = match self.renamed.get(&id) {
+            Some(&(original, ref scope)) => (original, scope.clone()),
+            None => return None,
+        };
+        let caller_scope = mem::replace(&mut self.current_scope, scope);
+        let res = self.lookup_symbol(original);
+        self.current_scope = caller_scope;
+        res
+    }
 
-        // TODO handle condition properly
-        match res.get_condition() {
-            Some(x) => panic!("{}", x.to_string(&self.interner)),
-            _ => (),
+    // calls func with already evaluated args, conditions are raised like in evaluate
+    pub fn apply(&mut self, func: &Value, args: &[Value]) -> Value {
+        let res = if let Some(native) = func.get_native_proc() {
+            native.apply(self, args)
+        } else if let Some(p) = func.get_proc() {
+            p.apply(self, args)
+        } else {
+            Value::new_condition(Value::new_string(format!("tried to call {}, which is not possible", func.to_string(&self.interner))))
         };
+        if res.get_condition().is_some() {
+            self.raise(&res);
+        }
         res
     }
 
+    // Calls a procedure, given by its name or as value, with args which are already evaluated,
+    // eg. interpreter.call("map", (inc, vec![1, 2])).
+    // Errors are an Error::Condition like in eval_str, not the condition Value: conditions reach catch_conditions
+    // as the message of an unwind, whose payload has to be Send, which the Rc based Values aren't.
+    pub fn call<C: Callee, A: IntoArgs>(&mut self, func: C, args: A) -> Result<Value, Error> {
+        let func = func.resolve(self)?;
+        let args = args.into_args();
+        self.catch_conditions(|interpreter| interpreter.apply(&func, &args))
+    }
+
+    // binds name in the builtins scope, so it's visible everywhere, including modules
+    pub fn define(&mut self, name: &str, value: Value) {
+        let id = self.interner.intern(name);
+        self.builtins.add_symbol(id, value);
+    }
+
+    // f gets the evaluated args and can capture state, Err is a condition, eg. Value::new_condition(Value::new_string(msg))
+    pub fn register_native<F>(&mut self, name: &str, arity: Arity, f: F)
+    where F: Fn(&mut Interpreter, &[Value]) -> Result<Value, Condition> + 'static {
+        self.define(name, Value::new_native_proc(NativeProc::new(name, arity, f)));
+    }
+
+    // registers a rust fn, whose args and result are converted from and into flip values, eg. |a: i64, b: i64| a + b
+    pub fn register_fn<F: IntoNative<Args>, Args>(&mut self, name: &str, f: F) {
+        self.define(name, Value::new_native_proc(f.into_native(name)));
+    }
+
+    // all names which can be used in the current scope, eg. for autocompletion
+    pub fn defined_names(&self) -> Vec<String> {
+        self.current_scope.symbol_ids()
+        .into_iter()
+        .filter_map(|id| self.interner.lookup(id).map(String::from))
+        .chain(self.syntax.keys().filter_map(|&id| self.interner.lookup(id).map(String::from)))
+        .chain(module::qualified_exports(self))
+        .collect()
+    }
+
+    // natives return conditions as values, apply passes them on like an Err
+    fn add_builtin(&mut self, name: &str, arity: Arity, f: fn(&mut Interpreter, &[Value]) -> Value) {
+        let native = NativeProc::new(name, arity, move |interpreter, args| Ok(f(interpreter, args)));
+        self.add_str_to_current_scope(name, Value::new_native_proc(native));
+    }
+
+    fn add_syntax(&mut self, name: &str, f: fn(&mut Interpreter, &[Value]) -> Value) {
+        let id = self.interner.intern(name);
+        self.syntax.insert(id, f);
+    }
+
     fn add_str_to_current_scope(&mut self, s: &str, value: Value) {
         let id = self.interner.intern(s);
         self.current_scope.add_symbol(id, value);
     }
 }
+
+// a procedure or the name of one, see Interpreter::call
+pub trait Callee {
+    fn resolve(self, interpreter: &mut Interpreter) -> Result<Value, Error>;
+}
+
+impl<'a> Callee for &'a str {
+    // names are looked up like symbols in the current scope, so qualified names like str/join work too
+    fn resolve(self, interpreter: &mut Interpreter) -> Result<Value, Error> {
+        let id = interpreter.interner.intern(self);
+        interpreter.lookup_symbol(id).ok_or_else(|| Error::Condition(format!("undefined ident: {}", self)))
+    }
+}
+
+impl<'a> Callee for &'a Value {
+    fn resolve(self, _: &mut Interpreter) -> Result<Value, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl Callee for Value {
+    fn resolve(self, _: &mut Interpreter) -> Result<Value, Error> {
+        Ok(self)
+    }
+}
@@ -0,0 +1,40 @@
+//! flip, a small lisp interpreter which can be embedded in rust programs, see `interpreter::Interpreter`.
+//!
+//! Conditions, flip's errors, unwind the rust stack up to `eval_str`, `load_file` or `call`, which return them as `Err`.
+//! So flip requires `panic = "unwind"`, the default: with `panic = "abort"` every condition would abort the host,
+//! which is why building with it is a compile error.
+
+#![feature(conservative_impl_trait)]
+#[macro_use] extern crate itertools;
+
+extern crate siphasher;
+extern crate lalrpop_util;
+
+#[cfg(panic = "abort")]
+compile_error!("flip raises conditions by unwinding, it can't be built with panic = \"abort\"");
+
+#[macro_use]
+mod native;
+
+mod error;
+mod compiler;
+mod expander;
+mod gc;
+pub mod grammar;
+pub mod value;
+pub mod interpreter;
+mod limits;
+mod loader;
+mod module;
+pub mod port;
+mod scope;
+pub mod string_interner;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use error::Error;
+pub use interpreter::{Interpreter, Capability};
+pub use limits::Limits;
+pub use gc::GcStats;
+pub use value::{Value, Condition};
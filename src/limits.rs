@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use ::value::Value;
+
+// Bounds for the evaluation of untrusted code, nothing is limited by default.
+// Exceeding a limit raises a condition, every limit has its own message.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    // evaluation steps left, every call of Interpreter::evaluate is one step
+    pub fuel: Option<u64>,
+    // time every eval_str, load_file and call may take
+    pub timeout: Option<Duration>,
+    // end of the timeout of the current eval
+    deadline: Option<Instant>,
+    // max number of elements of a list or bytes of a string
+    pub max_size: Option<usize>,
+    // max number of nested evaluations, deep recursion would overflow the rust stack and abort the host
+    pub max_depth: Option<usize>,
+    // can be set from another thread or a signal handler, eg. on Ctrl-C, it is reset when the condition is raised
+    pub interrupt: Arc<AtomicBool>,
+}
+
+impl Limits {
+    // called when an eval starts, which isn't nested in another one
+    pub fn start(&mut self) {
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    // called before every evaluation step
+    pub fn step(&mut self) -> Result<(), String> {
+        if self.interrupt.swap(false, Ordering::SeqCst) {
+            return Err("interrupted".to_string());
+        }
+        if let Some(fuel) = self.fuel {
+            if fuel == 0 {
+                return Err("out of fuel".to_string());
+            }
+            self.fuel = Some(fuel - 1);
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err("timeout: the deadline for evaluation passed".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // called with the number of evaluations in progress, before one more is started
+    pub fn check_depth(&self, depth: usize) -> Result<(), String> {
+        match self.max_depth {
+            Some(max) if depth >= max => Err(format!("depth limit exceeded: more than {} nested evaluations", max)),
+            _ => Ok(()),
+        }
+    }
+
+    // called with the result of every evaluation step
+    pub fn check_size(&self, value: &Value) -> Result<(), String> {
+        match (value.get_string(), value.list_len()) {
+            (Some(s), _) => self.check_string_len(s.len()),
+            (_, Some(len)) => self.check_list_len(len),
+            _ => Ok(()),
+        }
+    }
+
+    // natives check the size of the lists and strings they create before allocating them
+    pub fn check_list_len(&self, len: usize) -> Result<(), String> {
+        match self.max_size {
+            Some(max) if len > max => Err(format!("size limit exceeded: list with {} elements, the limit is {}", len, max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_string_len(&self, len: usize) -> Result<(), String> {
+        match self.max_size {
+            Some(max) if len > max => Err(format!("size limit exceeded: string with {} bytes, the limit is {}", len, max)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use ::value::Value;
+use ::interpreter::{Interpreter, Capability};
+use ::grammar::{self, error_printing};
+
+// Makes path relative to the directory of the file which is currently loaded, or the working directory,
+// and pushes it on the load stack. Loading a file which is still being loaded is an error.
+// The caller has to pop the load stack afterwards.
+pub fn enter(interpreter: &mut Interpreter, path: &str) -> Result<PathBuf, Value> {
+    if !interpreter.has_capability(Capability::FileSystem) {
+        return Err(new_condition!(format!("couldn't open {}: no access to the file system", path)));
+    }
+
+    let relative_to = interpreter.load_stack.last().and_then(|file| file.parent()).map(Path::to_path_buf);
+    let path = match relative_to {
+        Some(dir) => dir.join(path),
+        None => PathBuf::from(path),
+    };
+
+    let path = fs::canonicalize(&path)
+    .map_err(|err| new_condition!(format!("couldn't open {}: {}", path.display(), err)))?;
+    if interpreter.load_stack.contains(&path) {
+        let cycle: Vec<String> = interpreter.load_stack.iter().chain(Some(&path)).map(|x| x.display().to_string()).collect();
+        return Err(new_condition!(format!("cyclic load: {}", cycle.join(" -> "))));
+    }
+
+    interpreter.load_stack.push(path.clone());
+    Ok(path)
+}
+
+fn read_source(path: &Path) -> Result<String, Value> {
+    let mut source = String::new();
+    File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut source))
+    .map_err(|err| new_condition!(format!("couldn't read {}: {}", path.display(), err)))?;
+    Ok(source)
+}
+
+// parses the file as code
+fn parse_file(interpreter: &mut Interpreter, path: &Path) -> Result<Vec<Value>, Value> {
+    let source = read_source(path)?;
+    if source.trim().len() == 0 {
+        return Ok(vec![]);
+    }
+    grammar::parse(&source, &mut interpreter.interner)
+    .map_err(|err| new_condition!(format!("in file {}:\n{}", path.display(), error_printing::create_error_message(&source, &err))))
+}
+
+// reads the file as data, like macros get it
+pub fn read_file(interpreter: &mut Interpreter, path: &Path) -> Result<Vec<Value>, Value> {
+    let source = read_source(path)?;
+    if source.trim().len() == 0 {
+        return Ok(vec![]);
+    }
+    grammar::read(&source, &mut interpreter.interner)
+    .map_err(|err| new_condition!(format!("in file {}:\n{}", path.display(), error_printing::create_error_message(&source, &err))))
+}
+
+// evaluates all top-level forms of the file and returns the result of the last one
+pub fn load(interpreter: &mut Interpreter, path: &str) -> Value {
+    let path = match enter(interpreter, path) {
+        Ok(path) => path,
+        Err(condition) => return condition,
+    };
+
+    let mut res = Value::empty_list();
+    match parse_file(interpreter, &path) {
+        Ok(code) => for x in &code {
+            res = interpreter.evaluate_top_level(x);
+        },
+        Err(condition) => res = condition,
+    }
+    interpreter.load_stack.pop();
+    res
+}
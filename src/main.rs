@@ -1,38 +1,33 @@
-#![feature(conservative_impl_trait)]
 #[macro_use] extern crate clap;
-#[macro_use] extern crate itertools;
 
 extern crate rustyline;
-extern crate siphasher;
-extern crate lalrpop_util;
-
-#[macro_use]
-mod native;
+extern crate ctrlc;
+extern crate flip;
 
 mod cli;
-mod grammar;
-mod value;
-mod interpreter;
 mod repl;
-mod scope;
-mod string_interner;
 
-use std::io::Read;
-use ::value::Value;
+use std::process::exit;
+use flip::Interpreter;
 
 fn main() {
-    if let Some(mut file) = cli::get_args() {
-        let mut input = String::new();
-        file.read_to_string(&mut input).expect("Couldn't read file");
-        let mut interpreter = interpreter::Interpreter::new();
-        let parsed = grammar::parse(&input, &mut interpreter.interner).unwrap();
+    let args = cli::get_args();
+    let mut interpreter = if args.prelude {
+        Interpreter::new()
+    } else {
+        Interpreter::without_prelude()
+    };
 
-        let mut result = Value::empty_list();
-        for x in &parsed {
-            result = interpreter.evaluate(x);
+    if let Some(path) = args.path {
+        match interpreter.load_file(&path) {
+            Ok(ref result) if args.print_result => println!("=> {}", result.to_string(interpreter.interner())),
+            Ok(_) => (),
+            Err(err) => {
+                println!("{}", err);
+                exit(-1);
+            },
         }
-        println!("=> {}", result.to_string(&interpreter.interner))
     } else {
-        repl::Repl::start();
+        repl::Repl::start(interpreter);
     }
 }
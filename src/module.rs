@@ -0,0 +1,53 @@
+use ::value::Value;
+use ::scope::Scope;
+use ::interpreter::Interpreter;
+
+// The definitions of a module live in its own scope, which only sees the builtins and what the module imports.
+// Code outside of the module can only use the exported symbols.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub scope: Scope,
+    pub exports: Vec<u64>,
+}
+
+impl Module {
+    pub fn lookup_export(&self, id: u64) -> Option<Value> {
+        if self.exports.contains(&id) { self.scope.lookup_local(id) } else { None }
+    }
+}
+
+// looks up a qualified symbol like str/join, which refers to join exported by the module str,
+// if str was imported into the current scope
+pub fn lookup_qualified(interpreter: &mut Interpreter, id: u64) -> Option<Value> {
+    let (module, name) = match interpreter.interner.lookup(id).and_then(split_qualified) {
+        Some((module, name)) => (module.to_string(), name.to_string()),
+        None => return None,
+    };
+
+    let module = interpreter.interner.intern(module);
+    let name = interpreter.interner.intern(name);
+    if !interpreter.current_scope.has_import(module) {
+        return None;
+    }
+    interpreter.modules.get(&module).and_then(|module| module.lookup_export(name))
+}
+
+fn split_qualified(s: &str) -> Option<(&str, &str)> {
+    match s.find('/') {
+        Some(i) if i > 0 && i + 1 < s.len() => Some((&s[..i], &s[i + 1..])),
+        _ => None,
+    }
+}
+
+// the exported symbols of the imported modules in their qualified form, eg. for autocompletion
+pub fn qualified_exports(interpreter: &Interpreter) -> Vec<String> {
+    let mut names = vec![];
+    for (&module, m) in interpreter.modules.iter().filter(|&(&module, _)| interpreter.current_scope.has_import(module)) {
+        for &name in &m.exports {
+            if let (Some(module), Some(name)) = (interpreter.interner.lookup(module), interpreter.interner.lookup(name)) {
+                names.push(format!("{}/{}", module, name));
+            }
+        }
+    }
+    names
+}
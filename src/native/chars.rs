@@ -0,0 +1,88 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+
+// the first char of every run of decimal digits 0-9, from the Nd category of unicode 14.0
+const DIGIT_ZEROS: &'static [u32] = &[
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6,
+    0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66, 0xDE6, 0xE50, 0xED0,
+    0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80,
+    0x1A90, 0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900,
+    0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10, 0x104A0, 0x10D30, 0x11066,
+    0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0, 0x11650, 0x116C0,
+    0x11730, 0x118E0, 0x11950, 0x11C50, 0x11D50, 0x11DA0, 0x16A60, 0x16AC0,
+    0x16B50, 0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0,
+    0x1E950, 0x1FBF0,
+];
+
+// the value of a decimal digit of any script, eg. 3 for ٣
+fn decimal_digit(c: char) -> Option<u32> {
+    let c = c as u32;
+    match DIGIT_ZEROS.binary_search(&c) {
+        Ok(_) => Some(0),
+        Err(0) => None,
+        Err(i) if c - DIGIT_ZEROS[i - 1] < 10 => Some(c - DIGIT_ZEROS[i - 1]),
+        Err(_) => None,
+    }
+}
+
+fn is_decimal_digit(c: char) -> bool {
+    decimal_digit(c).is_some()
+}
+
+// classification uses the unicode tables of rust's char, except for digits
+macro_rules! char_predicate {
+    ($func:ident, $lisp_name:expr, $predicate:path) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let c = try_unwrap_type!($lisp_name, "char", Value::get_char, &args[0], interpreter);
+        Value::new_bool($predicate(c))
+    });
+}
+
+char_predicate!(char_alphabetic, "char-alphabetic?", char::is_alphabetic);
+char_predicate!(char_numeric, "char-numeric?", is_decimal_digit);
+char_predicate!(char_whitespace, "char-whitespace?", char::is_whitespace);
+char_predicate!(char_upper_case, "char-upper-case?", char::is_uppercase);
+char_predicate!(char_lower_case, "char-lower-case?", char::is_lowercase);
+
+// chars whose conversion is more than one char, like ß, stay as they are
+macro_rules! char_conversion {
+    ($func:ident, $lisp_name:expr, $conversion_fn:path) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let c = try_unwrap_type!($lisp_name, "char", Value::get_char, &args[0], interpreter);
+        let mut converted = $conversion_fn(c);
+        match (converted.next(), converted.next()) {
+            (Some(converted), None) => Value::new_char(converted),
+            _ => Value::new_char(c),
+        }
+    });
+}
+
+char_conversion!(char_upcase, "char-upcase", char::to_uppercase);
+char_conversion!(char_downcase, "char-downcase", char::to_lowercase);
+
+// (digit-value c), returns false if c isn't a decimal digit, like char-numeric?
+pub fn digit_value(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let c = try_unwrap_type!("digit-value", "char", Value::get_char, &args[0], interpreter);
+    match decimal_digit(c) {
+        Some(digit) => Value::new_integer(digit as i64),
+        None => Value::new_bool(false),
+    }
+}
+
+// Comparison operators
+macro_rules! char_comparison_operator {
+    ($func:ident, $lisp_name:expr, $operator:path) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let mut chars = vec![];
+        for x in args.iter() {
+            chars.push(try_unwrap_type!($lisp_name, "char", Value::get_char, x, interpreter));
+        }
+        Value::new_bool(chars.windows(2).all(|window| $operator(&window[0], &window[1])))
+    });
+}
+
+char_comparison_operator!(char_eq, "char=?", PartialEq::eq);
+char_comparison_operator!(char_lt, "char<?", PartialOrd::lt);
+char_comparison_operator!(char_le, "char<=?", PartialOrd::le);
+char_comparison_operator!(char_gt, "char>?", PartialOrd::gt);
+char_comparison_operator!(char_ge, "char>=?", PartialOrd::ge);
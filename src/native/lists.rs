@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use ::value::Value;
+use ::interpreter::Interpreter;
+
+// (apply f arg* list)
+pub fn apply(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let (last, init) = args.split_last().unwrap();
+    let list = try_unwrap_type!("apply", "list", Value::get_list, last, interpreter);
+    let call_args: Vec<Value> = init[1..].iter().cloned().chain(list).collect();
+    interpreter.apply(&init[0], &call_args)
+}
+
+// (map f list+), stops at the end of the shortest list
+pub fn map(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut lists = vec![];
+    for arg in &args[1..] {
+        lists.push(try_unwrap_type!("map", "list", Value::get_list, arg, interpreter));
+    }
+
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    let mut res = Vec::with_capacity(len);
+    for i in 0..len {
+        let call_args: Vec<Value> = lists.iter().map(|list| list[i].clone()).collect();
+        res.push(interpreter.apply(&args[0], &call_args));
+    }
+    Value::new_list(&res)
+}
+
+// (filter predicate list)
+pub fn filter(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("filter", "list", Value::get_list, &args[1], interpreter);
+
+    let mut res = vec![];
+    for x in list {
+        let keep = interpreter.apply(&args[0], &[x.clone()]);
+        let keep = try_unwrap_type!("filter predicate", "bool", Value::get_bool, &keep, interpreter);
+        if keep { res.push(x); }
+    }
+    Value::new_list(&res)
+}
+
+// (fold f init list), calls (f acc x) for each x from left to right
+pub fn fold(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("fold", "list", Value::get_list, &args[2], interpreter);
+
+    let mut acc = args[1].clone();
+    for x in list {
+        acc = interpreter.apply(&args[0], &[acc, x]);
+    }
+    acc
+}
+
+// (reduce f list), like fold with the first element as init
+pub fn reduce(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("reduce", "list", Value::get_list, &args[1], interpreter);
+    assert_or_condition!(list.len() > 0, "reduce expected list with len > 0");
+
+    let mut acc = list[0].clone();
+    for x in list.into_iter().skip(1) {
+        acc = interpreter.apply(&args[0], &[acc, x]);
+    }
+    acc
+}
+
+pub fn append(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut lists = vec![];
+    for arg in args.iter() {
+        lists.push(try_unwrap_type!("append", "list", Value::get_list, arg, interpreter));
+    }
+    try_condition!(interpreter.check_list_len(lists.iter().map(Vec::len).sum()));
+    let res: Vec<Value> = lists.into_iter().flat_map(|list| list).collect();
+    Value::new_list(&res)
+}
+
+pub fn reverse(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut list = try_unwrap_type!("reverse", "list", Value::get_list, &args[0], interpreter);
+    list.reverse();
+    Value::new_list(&list)
+}
+
+pub fn length(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("length", "list", Value::get_list, &args[0], interpreter);
+    Value::new_integer(list.len() as i64)
+}
+
+// (nth list n), n starts at 0
+pub fn nth(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("nth", "list", Value::get_list, &args[0], interpreter);
+    let n = try_unwrap_type!("nth", "integer", Value::get_integer, &args[1], interpreter);
+    assert_or_condition!(n >= 0 && (n as usize) < list.len(), format!("nth: index {} out of bounds for list with len {}", n, list.len()));
+    list[n as usize].clone()
+}
+
+// (take n list), the whole list if it's shorter than n
+pub fn take(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let n = try_unwrap_type!("take", "integer", Value::get_integer, &args[0], interpreter);
+    let list = try_unwrap_type!("take", "list", Value::get_list, &args[1], interpreter);
+    let n = if n < 0 { 0 } else { n as usize };
+    Value::new_list(&list[..list.len().min(n)])
+}
+
+// (drop n list), () if the list is shorter than n
+pub fn drop(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let n = try_unwrap_type!("drop", "integer", Value::get_integer, &args[0], interpreter);
+    let list = try_unwrap_type!("drop", "list", Value::get_list, &args[1], interpreter);
+    let n = if n < 0 { 0 } else { n as usize };
+    Value::new_list(&list[list.len().min(n)..])
+}
+
+// (range end) or (range start end), end is exclusive
+pub fn range(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut bounds = vec![];
+    for arg in args.iter() {
+        bounds.push(try_unwrap_type!("range", "integer", Value::get_integer, arg, interpreter));
+    }
+    let (start, end) = if bounds.len() == 1 { (0, bounds[0]) } else { (bounds[0], bounds[1]) };
+    let len = if end > start { end.wrapping_sub(start) as u64 } else { 0 };
+    try_condition!(interpreter.check_list_len(len as usize));
+    let res: Vec<Value> = (start..end).map(Value::new_integer).collect();
+    Value::new_list(&res)
+}
+
+// (sort list less?), without less? integers, chars and strings are sorted in ascending order
+pub fn sort(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("sort", "list", Value::get_list, &args[0], interpreter);
+    let less = args.get(1).cloned();
+    let sorted = try_condition!(merge_sort(interpreter, list, &less));
+    Value::new_list(&sorted)
+}
+
+// merge sort, because less? is a flip procedure, which can fail or be inconsistent
+fn merge_sort(interpreter: &mut Interpreter, mut list: Vec<Value>, less: &Option<Value>) -> Result<Vec<Value>, Value> {
+    if list.len() < 2 {
+        return Ok(list);
+    }
+
+    let right = list.split_off(list.len() / 2);
+    let left = merge_sort(interpreter, list, less)?;
+    let right = merge_sort(interpreter, right, less)?;
+
+    let mut res = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    loop {
+        let take_right = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => is_less(interpreter, r, l, less)?,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => return Ok(res),
+        };
+        res.push(if take_right { right.next().unwrap() } else { left.next().unwrap() });
+    }
+}
+
+fn is_less(interpreter: &mut Interpreter, a: &Value, b: &Value, less: &Option<Value>) -> Result<bool, Value> {
+    if let &Some(ref less) = less {
+        let res = interpreter.apply(less, &[a.clone(), b.clone()]);
+        return match res.get_bool() {
+            Some(b) => Ok(b),
+            None => Err(new_condition!(format!("sort expected bool from less?, got: {}", res.to_string(&interpreter.interner)))),
+        };
+    }
+
+    let ordering = match (a.get_integer(), b.get_integer(), a.get_char(), b.get_char(), a.get_string(), b.get_string()) {
+        (Some(a), Some(b), _, _, _, _) => a.cmp(&b),
+        (_, _, Some(a), Some(b), _, _) => a.cmp(&b),
+        (_, _, _, _, Some(a), Some(b)) => a.cmp(b),
+        _ => return Err(new_condition!(format!("sort can't compare {} and {}, supply less?",
+            a.to_string(&interpreter.interner), b.to_string(&interpreter.interner)))),
+    };
+    Ok(ordering == Ordering::Less)
+}
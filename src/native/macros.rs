@@ -1,32 +1,3 @@
-// this is used to automate arity checking of native functions
-// name is always the functions name as string, eg. for define name is "define"
-// len is always the number of arguments
-macro_rules! check_arity {
-    // exact arity
-    ($name:expr, $len:expr, $exact:expr) => ({
-        let len = $len as u32;
-        if len != $exact {
-            raise_condition!(format!("arity mismatch for {}: expected: {}, got: {}", $name, $exact, len));
-        }
-    });
-
-    // range
-    ($name:expr, $len:expr, $lo:expr, $hi:expr) => ({
-        let len = $len as u32;
-        if len < $lo || len > $hi {
-            raise_condition!(format!("arity mismatch for {}: expected: {}..{}, got: {}", $name, $lo, $hi, len));
-        }
-    });
-
-    // minimum
-    ($name:expr, $len:expr, min => $min:expr) => ({
-        let len = $len as u32;
-        if len < $min {
-            raise_condition!(format!("arity mismatch for {}: expected: {}.., got: {}", $name, $min, len));
-        }
-    });
-}
-
 // this automates type checking. If the expected type is not found, a condition is returned early
 // name: functions name
 // type_name: name of type, eg. "string" or "list"
@@ -65,30 +36,12 @@ macro_rules! assert_or_condition {
     })
 }
 
-// This automates the evaluation of arguments supplied to functions
-// arguments are just evaluated in order
-macro_rules! eval_args {
-    // args is an array of already evaluated args
-    (fn $func:ident($args:ident : $arg_ty:ty) -> $ret_ty:ty $blk:block) =>
-    (
-        pub fn $func(interpreter: &mut Interpreter, args: $arg_ty) -> $ret_ty {
-            fn inner($args: $arg_ty) -> $ret_ty $blk;
-            for x in args.iter_mut() {
-                *x = interpreter.evaluate(x);
-            }
-            inner(args)
+// returns the condition of a Result<_, Value> from the native
+macro_rules! try_condition {
+    ($e:expr) => ({
+        match $e {
+            Ok(x) => x,
+            Err(condition) => return condition,
         }
-    );
-
-    // same as above, but with interpreter available
-    (fn $func:ident($interpreter:ident : &mut Interpreter, $args:ident : $arg_ty:ty) -> $ret_ty:ty $blk:block) =>
-    (
-        pub fn $func(interpreter: &mut Interpreter, args: $arg_ty) -> $ret_ty {
-            fn inner($interpreter: &mut Interpreter, $args: $arg_ty) -> $ret_ty $blk;
-            for x in args.iter_mut() {
-                *x = interpreter.evaluate(x);
-            }
-            inner(interpreter, args)
-        }
-    );
+    });
 }
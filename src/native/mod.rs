@@ -3,3 +3,24 @@ mod macros;
 
 mod primitive_forms;
 pub use self::primitive_forms::*;
+
+mod lists;
+pub use self::lists::*;
+
+mod strings;
+pub use self::strings::*;
+
+mod chars;
+pub use self::chars::*;
+
+mod modules;
+pub use self::modules::*;
+
+mod ports;
+pub use self::ports::*;
+
+mod output;
+pub use self::output::*;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::mem;
+use ::value::{Value, Arity};
+use ::interpreter::Interpreter;
+use ::module::Module;
+use ::loader;
+
+// (module name (export symbol*) body*)
+// the body is evaluated in a fresh scope, which only sees the builtins, args aren't evaluated
+pub fn module(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    try_condition!(Arity::AtLeast(2).check("module", args.len()));
+    let name = try_unwrap_type!("module", "symbol", Value::get_symbol, &args[0], interpreter);
+    let exports = try_condition!(export_list(interpreter, &args[1]));
+
+    let scope = interpreter.builtins.new_child();
+    let caller_scope = mem::replace(&mut interpreter.current_scope, scope.clone());
+    for form in &args[2..] {
+        // top-level, so macros defined in the module can be used in its following forms
+        interpreter.evaluate_top_level(form);
+    }
+    interpreter.current_scope = caller_scope;
+
+    // only the module's own definitions and imports can be exported, not the builtins
+    for &id in &exports {
+        assert_or_condition!(scope.lookup_local(id).is_some(),
+            format!("module {}: exported {} isn't defined", args[0].to_string(&interpreter.interner), Value::new_symbol(id).to_string(&interpreter.interner)));
+    }
+    interpreter.modules.insert(name, Module { scope: scope, exports: exports });
+    args[0].clone()
+}
+
+// (export symbol*)
+fn export_list(interpreter: &mut Interpreter, exports: &Value) -> Result<Vec<u64>, Value> {
+    let export = interpreter.interner.intern("export");
+    let list = exports.get_list().unwrap_or(vec![]);
+    let symbols: Option<Vec<u64>> = list.iter().map(Value::get_symbol).collect();
+    match symbols {
+        Some(ref symbols) if symbols.first() == Some(&export) => Ok(symbols[1..].to_vec()),
+        _ => Err(new_condition!(format!("module expected (export symbol*), got: {}", exports.to_string(&interpreter.interner)))),
+    }
+}
+
+// (import (name) option* ..), args aren't evaluated
+// options: :only (symbol*), :prefix symbol, :rename ((from to)*)
+// renamed symbols are bound as they are, all others get the prefix
+// a module which isn't defined yet is loaded from name.flip, relative to the file being loaded
+pub fn import(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    try_condition!(Arity::AtLeast(1).check("import", args.len()));
+    let mut imported = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        let spec = args[i].clone();
+        i += 1;
+        let start = i;
+        while i + 1 < args.len() && is_option(interpreter, &args[i]) {
+            i += 2;
+        }
+        imported.extend(try_condition!(import_module(interpreter, &spec, &args[start..i])));
+    }
+    Value::new_list(&imported)
+}
+
+fn is_option(interpreter: &Interpreter, value: &Value) -> bool {
+    value.get_symbol().and_then(|id| interpreter.interner.lookup(id)).map_or(false, |s| s.starts_with(':'))
+}
+
+// binds the symbols exported by the module to the current scope, returns the bound symbols
+// qualified names like name/symbol can be used in the current scope afterwards, for all exports
+fn import_module(interpreter: &mut Interpreter, spec: &Value, options: &[Value]) -> Result<Vec<Value>, Value> {
+    let name = match spec.get_list() {
+        Some(ref list) if list.len() == 1 && list[0].get_symbol().is_some() => list[0].get_symbol().unwrap(),
+        _ => return Err(new_condition!(format!("import expected (module-name), got: {}", spec.to_string(&interpreter.interner)))),
+    };
+    let module = find_module(interpreter, name)?;
+    let module_name = Value::new_symbol(name).to_string(&interpreter.interner);
+
+    let mut symbols = module.exports.clone();
+    let mut prefix = String::new();
+    let mut renames = HashMap::new();
+    for option in options.chunks(2) {
+        let value = &option[1];
+        let invalid = || new_condition!(format!("import: invalid value for {}: {}",
+            option[0].to_string(&interpreter.interner), value.to_string(&interpreter.interner)));
+
+        match interpreter.interner.lookup(option[0].get_symbol().unwrap()) {
+            Some(":only") => {
+                let only: Option<Vec<u64>> = value.get_list().and_then(|l| l.iter().map(Value::get_symbol).collect());
+                symbols = only.ok_or_else(&invalid)?;
+            },
+            Some(":prefix") => prefix = value.get_symbol().and_then(|id| interpreter.interner.lookup(id)).ok_or_else(&invalid)?.to_string(),
+            Some(":rename") => for pair in value.get_list().ok_or_else(&invalid)? {
+                match pair.get_list().and_then(|p| p.iter().map(Value::get_symbol).collect::<Option<Vec<u64>>>()) {
+                    Some(ref p) if p.len() == 2 => { renames.insert(p[0], p[1]); },
+                    _ => return Err(invalid()),
+                }
+            },
+            _ => return Err(new_condition!(format!("import: unknown option {}", option[0].to_string(&interpreter.interner)))),
+        }
+    }
+
+    let mut imported = vec![];
+    for id in symbols {
+        let value = match module.lookup_export(id) {
+            Some(value) => value,
+            None => return Err(new_condition!(format!("import: module {} doesn't export {}",
+                module_name, Value::new_symbol(id).to_string(&interpreter.interner)))),
+        };
+        let bound = match renames.get(&id) {
+            Some(&renamed) => renamed,
+            None if prefix.is_empty() => id,
+            None => {
+                let name = format!("{}{}", prefix, Value::new_symbol(id).to_string(&interpreter.interner));
+                interpreter.interner.intern(name)
+            },
+        };
+        interpreter.current_scope.add_symbol(bound, value);
+        imported.push(Value::new_symbol(bound));
+    }
+    interpreter.current_scope.add_import(name);
+    Ok(imported)
+}
+
+fn find_module(interpreter: &mut Interpreter, name: u64) -> Result<Module, Value> {
+    if let Some(module) = interpreter.modules.get(&name) {
+        return Ok(module.clone());
+    }
+
+    let module_name = Value::new_symbol(name).to_string(&interpreter.interner);
+    let res = loader::load(interpreter, &format!("{}.flip", module_name));
+    if let Some(condition) = res.get_condition() {
+        return Err(new_condition!(format!("import: couldn't load module {}: {}", module_name, condition.to_display_string(&interpreter.interner))));
+    }
+    match interpreter.modules.get(&name) {
+        Some(module) => Ok(module.clone()),
+        None => Err(new_condition!(format!("import: {}.flip doesn't define module {}", module_name, module_name))),
+    }
+}
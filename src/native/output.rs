@@ -0,0 +1,36 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use itertools::Itertools;
+use super::ports::port_arg;
+
+// writes s to the port at args[i] or the current output port
+fn write_output(interpreter: &mut Interpreter, name: &str, args: &[Value], i: usize, s: &str) -> Value {
+    let port = try_condition!(port_arg(interpreter, name, args, i, &interpreter.current_output));
+    match port.write_str(s) {
+        Ok(()) => Value::empty_list(),
+        Err(msg) => new_condition!(format!("{}: couldn't write output: {}", name, msg)),
+    }
+}
+
+// (display x port?), strings and chars are written as they are
+pub fn display(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = args[0].to_display_string(&interpreter.interner);
+    write_output(interpreter, "display", args, 1, &s)
+}
+
+// (write x port?), in the form the parser reads back
+pub fn write(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = args[0].to_string(&interpreter.interner);
+    write_output(interpreter, "write", args, 1, &s)
+}
+
+pub fn newline(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    write_output(interpreter, "newline", args, 0, "\n")
+}
+
+// (println x*), displays all args separated by spaces followed by a newline on the current output port
+pub fn println(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut s = args.iter().map(|x| x.to_display_string(&interpreter.interner)).join(" ");
+    s.push('\n');
+    write_output(interpreter, "println", &[], 0, &s)
+}
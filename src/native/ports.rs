@@ -0,0 +1,220 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use ::port::Port;
+use ::grammar::{self, error_printing};
+
+// the port at args[i] or default, if there are less args
+pub fn port_arg(interpreter: &Interpreter, name: &str, args: &[Value], i: usize, default: &Value) -> Result<Port, Value> {
+    let port = args.get(i).unwrap_or(default);
+    match port.get_port() {
+        Some(port) => Ok(port.clone()),
+        None => Err(new_condition!(format!("{} expected port, got: {}", name, port.to_string(&interpreter.interner)))),
+    }
+}
+
+macro_rules! try_port {
+    ($name:expr, $e:expr) => ({
+        match $e {
+            Ok(x) => x,
+            Err(msg) => return new_condition!(format!("{}: {}", $name, msg)),
+        }
+    });
+}
+
+pub fn open_input_file(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let path = try_unwrap_type!("open-input-file", "string", Value::get_string, &args[0], interpreter);
+    Value::new_port(try_port!("open-input-file", Port::open_input_file(path).map_err(|err| format!("{}: {}", path, err))))
+}
+
+pub fn open_output_file(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let path = try_unwrap_type!("open-output-file", "string", Value::get_string, &args[0], interpreter);
+    Value::new_port(try_port!("open-output-file", Port::open_output_file(path).map_err(|err| format!("{}: {}", path, err))))
+}
+
+pub fn open_input_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("open-input-string", "string", Value::get_string, &args[0], interpreter);
+    Value::new_port(Port::input_string(s))
+}
+
+pub fn open_output_string(_: &mut Interpreter, _: &[Value]) -> Value {
+    Value::new_port(Port::output_string())
+}
+
+pub fn get_output_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_unwrap_type!("get-output-string", "port", Value::get_port, &args[0], interpreter);
+    match port.get_output_string() {
+        Some(s) => Value::new_string(s),
+        None => new_condition!(format!("get-output-string expected open output string port, got: {}", port.to_string())),
+    }
+}
+
+pub fn current_input_port(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    interpreter.current_input.clone()
+}
+
+pub fn current_output_port(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    interpreter.current_output.clone()
+}
+
+pub fn current_error_port(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    interpreter.current_error.clone()
+}
+
+pub fn input_port(_: &mut Interpreter, args: &[Value]) -> Value {
+    Value::new_bool(args[0].get_port().map_or(false, Port::is_input))
+}
+
+pub fn output_port(_: &mut Interpreter, args: &[Value]) -> Value {
+    Value::new_bool(args[0].get_port().map_or(false, Port::is_output))
+}
+
+pub fn eof_object(_: &mut Interpreter, args: &[Value]) -> Value {
+    Value::new_bool(args[0].get_eof().is_some())
+}
+
+// (read-char port?), returns an eof object at the end of the input
+pub fn read_char(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_condition!(port_arg(interpreter, "read-char", args, 0, &interpreter.current_input));
+    try_port!("read-char", port.read_char()).map_or(Value::eof(), Value::new_char)
+}
+
+pub fn peek_char(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_condition!(port_arg(interpreter, "peek-char", args, 0, &interpreter.current_input));
+    try_port!("peek-char", port.peek_char()).map_or(Value::eof(), Value::new_char)
+}
+
+// (read-line port?), the newline isn't part of the result
+pub fn read_line(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_condition!(port_arg(interpreter, "read-line", args, 0, &interpreter.current_input));
+    try_port!("read-line", port.read_line()).map_or(Value::eof(), Value::new_string)
+}
+
+// (write-string s port?)
+pub fn write_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("write-string", "string", Value::get_string, &args[0], interpreter);
+    let port = try_condition!(port_arg(interpreter, "write-string", args, 1, &interpreter.current_output));
+    try_port!("write-string", port.write_str(s));
+    Value::empty_list()
+}
+
+pub fn close_port(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_unwrap_type!("close-port", "port", Value::get_port, &args[0], interpreter);
+    try_port!("close-port", port.close());
+    Value::empty_list()
+}
+
+// (with-output-to-string thunk), returns everything thunk writes to the current output port
+pub fn with_output_to_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = Port::output_string();
+    let previous = ::std::mem::replace(&mut interpreter.current_output, Value::new_port(port.clone()));
+    // on a condition, the output port is restored by catch_conditions
+    interpreter.apply(&args[0], &[]);
+    interpreter.current_output = previous;
+    Value::new_string(port.get_output_string().unwrap_or(String::new()))
+}
+
+// a string argument is read like an input string port
+fn input_arg(interpreter: &Interpreter, name: &str, args: &[Value]) -> Result<Port, Value> {
+    match args.get(0).and_then(Value::get_string) {
+        Some(s) => Ok(Port::input_string(s)),
+        None => port_arg(interpreter, name, args, 0, &interpreter.current_input),
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+// moves chars up to the next delimiter from port to source, returns their number
+fn take_atom(port: &Port, source: &mut String) -> Result<usize, String> {
+    let mut n = 0;
+    while let Some(c) = port.peek_char()? {
+        if is_delimiter(c) { break; }
+        port.read_char()?;
+        source.push(c);
+        n += 1;
+    }
+    Ok(n)
+}
+
+// Takes the source of the next datum from the port, so it's parsed only once. The datum ends with the )
+// closing its first ( or after an atom outside of any list. Parens in strings and chars don't count and
+// quotes and tags are part of the datum they prefix. Returns an empty string at the end of the input.
+fn datum_source(port: &Port) -> Result<String, String> {
+    let mut source = String::new();
+    let mut depth = 0;
+    while let Some(c) = port.read_char()? {
+        if c.is_whitespace() && source.is_empty() {
+            continue;
+        }
+        source.push(c);
+
+        let complete = match c {
+            '(' => { depth += 1; false },
+            ')' => { depth -= 1; true },
+            '"' => {
+                loop {
+                    match port.read_char()? {
+                        Some('"') => { source.push('"'); break; },
+                        Some('\\') => {
+                            source.push('\\');
+                            source.extend(port.read_char()?);
+                        },
+                        Some(c) => source.push(c),
+                        None => return Ok(source),
+                    }
+                }
+                true
+            },
+            '\'' | '`' => false,
+            ',' => {
+                if port.peek_char()? == Some('@') {
+                    source.extend(port.read_char()?);
+                }
+                false
+            },
+            '#' if port.peek_char()? == Some('\\') => {
+                // the char itself might be a delimiter, like in #\(
+                source.extend(port.read_char()?);
+                source.extend(port.read_char()?);
+                take_atom(port, &mut source)?;
+                true
+            },
+            // #tag prefixes a datum, # alone is a symbol
+            '#' => take_atom(port, &mut source)? == 0,
+            c if c.is_whitespace() => false,
+            _ => { take_atom(port, &mut source)?; true },
+        };
+        if complete && depth <= 0 {
+            break;
+        }
+    }
+    Ok(source)
+}
+
+// (read port-or-string?), reads the next datum, returns an eof object at the end of the input
+pub fn read(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_condition!(input_arg(interpreter, "read", args));
+    let source = try_port!("read", datum_source(&port));
+    if source.is_empty() {
+        return Value::eof();
+    }
+    match grammar::read(&source, &mut interpreter.interner) {
+        Ok(ref mut data) if data.len() == 1 => data.pop().unwrap(),
+        Ok(_) => new_condition!(format!("read: invalid datum: {}", source)),
+        Err(ref err) => new_condition!(format!("read: {}", error_printing::create_error_message(&source, err))),
+    }
+}
+
+// (read-all port-or-string?), reads all data up to the end of the input into a list
+pub fn read_all(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let port = try_condition!(input_arg(interpreter, "read-all", args));
+    let source = try_port!("read-all", port.read_to_string());
+    if source.trim().len() == 0 {
+        return Value::empty_list();
+    }
+    match grammar::read(&source, &mut interpreter.interner) {
+        Ok(data) => Value::new_list(&data),
+        Err(ref err) => new_condition!(format!("read-all: {}", error_printing::create_error_message(&source, err))),
+    }
+}
@@ -1,61 +1,57 @@
-use std::ops::{Add, Sub, Mul, Div, Rem};
+use std::mem;
 use ::value::Value;
 use ::interpreter::Interpreter;
 use ::grammar;
+use ::expander;
+use ::loader;
 
 // Polymorphic equality
-eval_args!(fn poly_eq(args: &mut [Value]) -> Value {
-    check_arity!("eq?", args.len(), min => 2);
+pub fn poly_eq(_: &mut Interpreter, args: &[Value]) -> Value {
     Value::new_bool(args.windows(2).all(|window| window[0] == window[1]))
-});
+}
 
 // Type checking
 macro_rules! type_checker {
-    ($func:ident, $lisp_name:expr, $checking_fn:ident) =>
-    (eval_args!(fn $func(args: &mut [Value]) -> Value {
-        check_arity!($lisp_name, args.len(), 1);
+    ($func:ident, $checking_fn:ident) =>
+    (pub fn $func(_: &mut Interpreter, args: &[Value]) -> Value {
         Value::new_bool(args[0].$checking_fn().is_some())
-    }););
+    });
 }
 
-type_checker!(null_, "null?", get_empty_list);
-type_checker!(boolean_, "boolean?", get_bool);
-type_checker!(symbol_, "symbol?", get_symbol);
-type_checker!(integer_, "integer?", get_integer);
-type_checker!(char_, "char?", get_char);
-type_checker!(string_, "string?", get_string);
-type_checker!(list_, "list?", get_list);
+type_checker!(null_, get_empty_list);
+type_checker!(boolean_, get_bool);
+type_checker!(symbol_, get_symbol);
+type_checker!(integer_, get_integer);
+type_checker!(char_, get_char);
+type_checker!(string_, get_string);
+type_checker!(list_, get_list);
 
-eval_args!(fn procedure_(args: &mut [Value]) -> Value {
-    check_arity!("procedure?", args.len(), 1);
-    let is_proc = Value::get_native_fn_ptr(&args[0]).is_some() || Value::get_proc(&args[0]).is_some();
+pub fn procedure_(_: &mut Interpreter, args: &[Value]) -> Value {
+    let is_proc = Value::get_native_proc(&args[0]).is_some() || Value::get_proc(&args[0]).is_some();
     Value::new_bool(is_proc)
-});
+}
 
 // Type conversions
 macro_rules! type_conversion {
     ($func:ident, $lisp_name:expr, $type_name:expr, $get_fn:path, $conversion_fn:expr, $new_fn:path) =>
-    (eval_args!(fn $func(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-        check_arity!($lisp_name, args.len(), 1);
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
         let conversion_fn = $conversion_fn;
         let rust_value = try_unwrap_type!($lisp_name, $type_name, $get_fn, &args[0], interpreter);
         let converted = conversion_fn(rust_value);
         $new_fn(converted)
-    }););
+    });
 }
 
 
 
-eval_args!(fn char_integer(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("char->integer", args.len(), 1);
+pub fn char_integer(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     let c = try_unwrap_type!("char->integer", "char", Value::get_char, &args[0], interpreter);
     Value::new_integer(c as i64)
-});
+}
 
-eval_args!(fn integer_char(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
+pub fn integer_char(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     use std::u32;
     use std::char;
-    check_arity!("integer->char", args.len(), 1);
     let i = try_unwrap_type!("integer->char", "integer", Value::get_integer, &args[0], interpreter);
     if i > 0 && i < u32::MAX as i64 {
         let u = i as u32;
@@ -64,27 +60,23 @@ eval_args!(fn integer_char(interpreter: &mut Interpreter, args: &mut [Value]) ->
         }
     }
     raise_condition!("integer is not a valid char");
-});
+}
 
-eval_args!(fn number_string(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("number->string", args.len(), 1);
+pub fn number_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     let i = try_unwrap_type!("number->string", "integer", Value::get_integer, &args[0], interpreter);
     Value::new_string(format!("{}", i))
-});
+}
 
-eval_args!(fn string_number(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("string->number", args.len(), 1);
+pub fn string_number(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     let s = try_unwrap_type!("string->number", "string", Value::get_string, &args[0], interpreter);
     if let Ok(v) = grammar::parse_integer(s) {
         return v;
     }
     raise_condition!(format!("string is not a valid integer: {:?}", s));
-});
+}
 
-pub fn symbol_string(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("symbol->string", args.len(), 1);
-    let evaled = interpreter.evaluate(&args[0]);
-    let id = try_unwrap_type!("symbol->string", "symbol", Value::get_symbol, &evaled, interpreter);
+pub fn symbol_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let id = try_unwrap_type!("symbol->string", "symbol", Value::get_symbol, &args[0], interpreter);
     if let Some(string) = interpreter.interner.lookup(id) {
         Value::new_string(string)
     } else {
@@ -92,49 +84,59 @@ pub fn symbol_string(interpreter: &mut Interpreter, args: &mut [Value]) -> Value
     }
 }
 
-pub fn string_symbol(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("string->symbol", args.len(), 1);
-    let evaled = interpreter.evaluate(&args[0]);
-    let string = try_unwrap_type!("string->symbol", "string", Value::get_string, &evaled, interpreter);
+pub fn string_symbol(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let string = try_unwrap_type!("string->symbol", "string", Value::get_string, &args[0], interpreter);
     let id = interpreter.interner.intern(string);
     Value::new_symbol(id)
 }
 
 // Arithmetic operators
+// with one arg the result is unary applied to it, without args it's the default
+// the operator is checked, overflow and division by zero raise a condition instead of panicking
 macro_rules! arithmetic_operator {
-    ($func:ident, $operator:path, $default:expr) =>
-    (eval_args!(fn $func(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-        let mut res = if args.len() < 2 {
-            $default as i64
-        } else {
-            match args[0].get_integer() {
-                Some(i) => i,
-                None => raise_condition!(format!("expected integer, got: {}", &args[0].to_string(&interpreter.interner)))
-            }
+    ($func:ident, $name:expr, $operator:path, $default:expr) => (arithmetic_operator!($func, $name, $operator, $default, Some););
+    ($func:ident, $name:expr, $operator:path, $default:expr, $unary:expr) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        if args.len() == 0 {
+            return Value::new_integer($default as i64);
+        }
+        let mut res = match args[0].get_integer() {
+            Some(i) => i,
+            None => raise_condition!(format!("expected integer, got: {}", &args[0].to_string(&interpreter.interner)))
         };
+        if args.len() == 1 {
+            let unary: fn(i64) -> Option<i64> = $unary;
+            match unary(res) {
+                Some(res) => return Value::new_integer(res),
+                None => raise_condition!(format!("{}: integer overflow", $name)),
+            }
+        }
         for x in args[1..].iter() {
             if let Some(i) = x.get_integer() {
-                res = $operator(res, i);
+                res = match $operator(res, i) {
+                    Some(res) => res,
+                    None if i == 0 => raise_condition!(format!("{}: division by zero", $name)),
+                    None => raise_condition!(format!("{}: integer overflow", $name)),
+                };
             } else {
                 raise_condition!(format!("expected integer, got: {}", x.to_string(&interpreter.interner)))
             }
         }
         Value::new_integer(res)
-    }););
+    });
 }
 
-arithmetic_operator!(plus, Add::add, 0);
-arithmetic_operator!(minus, Sub::sub, 0);
-arithmetic_operator!(multiply, Mul::mul, 1);
-arithmetic_operator!(quotient, Div::div, 1);
-arithmetic_operator!(remainder, Rem::rem, 1);
+arithmetic_operator!(plus, "+", i64::checked_add, 0);
+arithmetic_operator!(minus, "-", i64::checked_sub, 0, i64::checked_neg);
+arithmetic_operator!(multiply, "*", i64::checked_mul, 1);
+// quotient and remainder take exactly two args, as in R7RS
+arithmetic_operator!(quotient, "quotient", i64::checked_div, 1);
+arithmetic_operator!(remainder, "remainder", i64::checked_rem, 1);
 
 // Comparison Operators
 macro_rules! comparison_operator {
-    ($func:ident, $lisp_name:expr, $operator:path) =>
-    (eval_args!(fn $func(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-        check_arity!($lisp_name, args.len(), min => 2);
-
+    ($func:ident, $operator:path) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
         let mut res = true;
         let compared_element = match args[0].get_integer() {
             Some(i) => i,
@@ -149,37 +151,97 @@ macro_rules! comparison_operator {
             res = res && $operator(&compared_element, &num);
         }
         Value::new_bool(res)
-    }););
+    });
 }
 
-comparison_operator!(eq, "=", PartialEq::eq);
-comparison_operator!(lt, "<", PartialOrd::lt);
-comparison_operator!(le, "<=", PartialOrd::le);
-comparison_operator!(gt, ">", PartialOrd::gt);
-comparison_operator!(ge, ">=", PartialOrd::ge);
+comparison_operator!(eq, PartialEq::eq);
+comparison_operator!(lt, PartialOrd::lt);
+comparison_operator!(le, PartialOrd::le);
+comparison_operator!(gt, PartialOrd::gt);
+comparison_operator!(ge, PartialOrd::ge);
 
 // List operations:
-eval_args!(fn first(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("first", args.len(), 1);
+pub fn first(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     let list = try_unwrap_type!("first", "list", Value::get_list, &args[0], interpreter);
     assert_or_condition!(list.len() > 0, "expected list with len > 0");
     list[0].clone()
-});
+}
 
-eval_args!(fn rest(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("rest", args.len(), 1);
+pub fn rest(interpreter: &mut Interpreter, args: &[Value]) -> Value {
     let list = try_unwrap_type!("rest", "list", Value::get_list, &args[0], interpreter);
     assert_or_condition!(list.len() > 0, "expected list with len > 0");
     Value::new_list(&list[1..])
-});
+}
+
+// lists are vectors, so this copies l once
+pub fn cons(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("cons", "list", Value::get_list, &args[1], interpreter);
+    let mut res = Vec::with_capacity(list.len() + 1);
+    res.push(args[0].clone());
+    res.extend(list);
+    Value::new_list(&res)
+}
 
-eval_args!(fn list(args: &mut [Value]) -> Value {
+pub fn list(_: &mut Interpreter, args: &[Value]) -> Value {
     Value::new_list(args)
-});
+}
+
+// Macros
+pub fn macroexpand_1(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    match expander::macroexpand_1(interpreter, &args[0]) {
+        Ok(expanded) => expanded.unwrap_or(args[0].clone()),
+        Err(condition) => condition,
+    }
+}
+
+pub fn macroexpand(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    match expander::macroexpand(interpreter, &args[0]) {
+        Ok(expanded) => expanded,
+        Err(condition) => condition,
+    }
+}
+
+// (load path), evaluates the file in the current scope, path is relative to the file being loaded
+pub fn load(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let path = try_unwrap_type!("load", "string", Value::get_string, &args[0], interpreter);
+    loader::load(interpreter, path)
+}
+
+// (eval data environment?), evaluates data as code in environment or the current scope
+pub fn eval(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let previous_scope = match args.get(1) {
+        Some(env) => {
+            let scope = try_unwrap_type!("eval", "environment", Value::get_environment, env, interpreter).clone();
+            Some(mem::replace(&mut interpreter.current_scope, scope))
+        },
+        None => None,
+    };
+
+    let res = match expander::data_to_code(interpreter, &args[0]) {
+        Ok(code) => interpreter.evaluate(&code),
+        Err(condition) => condition,
+    };
+
+    if let Some(scope) = previous_scope {
+        interpreter.current_scope = scope;
+    }
+    res
+}
 
-pub fn symbol_space(interpreter: &mut Interpreter, args: &mut [Value]) -> Value {
-    check_arity!("symbol-space", args.len(), 0);
+pub fn current_environment(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    Value::new_environment(interpreter.current_scope.clone())
+}
+
+// Tagged literals
+pub fn register_tag(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let tag = try_unwrap_type!("register-tag", "symbol", Value::get_symbol, &args[0], interpreter);
+    let is_proc = args[1].get_native_proc().is_some() || args[1].get_proc().is_some();
+    assert_or_condition!(is_proc, format!("register-tag expected procedure, got: {}", args[1].to_string(&interpreter.interner)));
+    interpreter.reader_tags.insert(tag, args[1].clone());
+    args[0].clone()
+}
 
+pub fn symbol_space(interpreter: &mut Interpreter, _: &[Value]) -> Value {
     let symbols: Vec<Value> = interpreter.current_scope.symbol_ids()
     .into_iter()
     .map(|s| Value::new_symbol(s))
@@ -187,3 +249,17 @@ pub fn symbol_space(interpreter: &mut Interpreter, args: &mut [Value]) -> Value
 
     Value::new_list(&symbols)
 }
+
+// (gc) frees cycles of unused procedures and scopes, returns the number of freed scopes
+pub fn gc(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    Value::new_integer(interpreter.collect_garbage() as i64)
+}
+
+// (gc-stats) returns ((collections n) (freed n) (live n))
+pub fn gc_stats(interpreter: &mut Interpreter, _: &[Value]) -> Value {
+    let stats = interpreter.gc_stats();
+    let stats: Vec<Value> = [("collections", stats.collections), ("freed", stats.freed), ("live", stats.live)].iter()
+    .map(|&(name, n)| Value::new_list(&[Value::new_symbol(interpreter.interner.intern(name)), Value::new_integer(n as i64)]))
+    .collect();
+    Value::new_list(&stats)
+}
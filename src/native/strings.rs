@@ -0,0 +1,134 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+
+// all indices count chars, not bytes
+
+pub fn string_length(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string-length", "string", Value::get_string, &args[0], interpreter);
+    Value::new_integer(s.chars().count() as i64)
+}
+
+pub fn string_append(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let mut strings = vec![];
+    for arg in args.iter() {
+        strings.push(try_unwrap_type!("string-append", "string", Value::get_string, arg, interpreter));
+    }
+    try_condition!(interpreter.check_string_len(strings.iter().map(|s| s.len()).sum()));
+    Value::new_string(strings.concat())
+}
+
+// (substring s start end?)
+pub fn substring(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("substring", "string", Value::get_string, &args[0], interpreter);
+    let len = s.chars().count() as i64;
+    let start = try_unwrap_type!("substring", "integer", Value::get_integer, &args[1], interpreter);
+    let end = match args.get(2) {
+        Some(end) => try_unwrap_type!("substring", "integer", Value::get_integer, end, interpreter),
+        None => len,
+    };
+    assert_or_condition!(0 <= start && start <= end && end <= len,
+        format!("substring: invalid range {}..{} for string with len {}", start, end, len));
+    Value::new_string(s.chars().skip(start as usize).take((end - start) as usize).collect::<String>())
+}
+
+pub fn string_ref(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string-ref", "string", Value::get_string, &args[0], interpreter);
+    let i = try_unwrap_type!("string-ref", "integer", Value::get_integer, &args[1], interpreter);
+    match s.chars().nth(i as usize) {
+        Some(c) if i >= 0 => Value::new_char(c),
+        _ => raise_condition!(format!("string-ref: index {} out of bounds for string with len {}", i, s.chars().count())),
+    }
+}
+
+// (string-split s separator)
+pub fn string_split(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string-split", "string", Value::get_string, &args[0], interpreter);
+    let separator = try_unwrap_type!("string-split", "string", Value::get_string, &args[1], interpreter);
+    assert_or_condition!(separator.len() > 0, "string-split expected non-empty separator");
+    let parts: Vec<Value> = s.split(separator).map(Value::new_string).collect();
+    Value::new_list(&parts)
+}
+
+// (string-join list separator?)
+pub fn string_join(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("string-join", "list", Value::get_list, &args[0], interpreter);
+    let separator = match args.get(1) {
+        Some(separator) => try_unwrap_type!("string-join", "string", Value::get_string, separator, interpreter),
+        None => "",
+    };
+
+    let mut parts = vec![];
+    for x in &list {
+        parts.push(try_unwrap_type!("string-join", "string", Value::get_string, x, interpreter));
+    }
+    let len = parts.iter().map(|s| s.len()).sum::<usize>() + separator.len() * parts.len().saturating_sub(1);
+    try_condition!(interpreter.check_string_len(len));
+    Value::new_string(parts.join(separator))
+}
+
+// (string-index s char-or-string), returns false if it isn't found
+pub fn string_index(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string-index", "string", Value::get_string, &args[0], interpreter);
+    let byte_index = match (args[1].get_char(), args[1].get_string()) {
+        (Some(c), _) => s.find(c),
+        (_, Some(needle)) => s.find(needle),
+        _ => raise_condition!(format!("string-index expected char or string, got: {}", args[1].to_string(&interpreter.interner))),
+    };
+    match byte_index {
+        Some(i) => Value::new_integer(s[..i].chars().count() as i64),
+        None => Value::new_bool(false),
+    }
+}
+
+pub fn string_contains(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string-contains?", "string", Value::get_string, &args[0], interpreter);
+    let needle = try_unwrap_type!("string-contains?", "string", Value::get_string, &args[1], interpreter);
+    Value::new_bool(s.contains(needle))
+}
+
+macro_rules! string_conversion {
+    ($func:ident, $lisp_name:expr, $conversion_fn:expr) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let s = try_unwrap_type!($lisp_name, "string", Value::get_string, &args[0], interpreter);
+        let conversion_fn = $conversion_fn;
+        Value::new_string(conversion_fn(s))
+    });
+}
+
+string_conversion!(string_upcase, "string-upcase", |s: &str| s.to_uppercase());
+string_conversion!(string_downcase, "string-downcase", |s: &str| s.to_lowercase());
+string_conversion!(string_trim, "string-trim", |s: &str| s.trim().to_string());
+
+pub fn string_list(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let s = try_unwrap_type!("string->list", "string", Value::get_string, &args[0], interpreter);
+    let chars: Vec<Value> = s.chars().map(Value::new_char).collect();
+    Value::new_list(&chars)
+}
+
+pub fn list_string(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+    let list = try_unwrap_type!("list->string", "list", Value::get_list, &args[0], interpreter);
+    let mut chars = vec![];
+    for x in &list {
+        chars.push(try_unwrap_type!("list->string", "char", Value::get_char, x, interpreter));
+    }
+    try_condition!(interpreter.check_string_len(chars.iter().map(|c| c.len_utf8()).sum()));
+    Value::new_string(chars.into_iter().collect::<String>())
+}
+
+// Comparison operators
+macro_rules! string_comparison_operator {
+    ($func:ident, $lisp_name:expr, $operator:path) =>
+    (pub fn $func(interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let mut strings = vec![];
+        for x in args.iter() {
+            strings.push(try_unwrap_type!($lisp_name, "string", Value::get_string, x, interpreter));
+        }
+        Value::new_bool(strings.windows(2).all(|window| $operator(&window[0], &window[1])))
+    });
+}
+
+string_comparison_operator!(string_eq, "string=?", PartialEq::eq);
+string_comparison_operator!(string_lt, "string<?", PartialOrd::lt);
+string_comparison_operator!(string_le, "string<=?", PartialOrd::le);
+string_comparison_operator!(string_gt, "string>?", PartialOrd::gt);
+string_comparison_operator!(string_ge, "string>=?", PartialOrd::ge);
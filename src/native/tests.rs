@@ -0,0 +1,607 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use ::grammar::parse;
+use ::port::Port;
+use ::value::Arity;
+use std::io::Write;
+use ::test_utils::{eval, eval_in, show};
+
+#[test]
+fn apply() {
+    assert_eq!(eval("(apply + (list 1 2 3))"), "6");
+    assert_eq!(eval("(apply + 1 2 (list 3))"), "6");
+    assert_eq!(eval("(apply (lambda (a & b) b) 1 (list 2 3))"), "(2 3)");
+    // already evaluated args aren't evaluated again
+    assert_eq!(eval("(apply list (list 'a '(b c)))"), "(a (b c))");
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, "(apply first '(1 2))"), "error: arity mismatch for first: expected: 1, got: 2");
+    assert_eq!(eval_in(&mut interpreter, "(apply substring '(\"abc\"))"), "error: arity mismatch for substring: expected: 2..3, got: 1");
+    // module and import aren't procedures
+    assert_eq!(eval_in(&mut interpreter, "(apply module '(m (export)))"), "error: undefined ident: module");
+    assert_eq!(eval_in(&mut interpreter, "(procedure? import)"), "error: undefined ident: import");
+}
+
+#[test]
+fn map_filter_fold() {
+    assert_eq!(eval("(map (lambda (x) (* x x)) (list 1 2 3))"), "(1 4 9)");
+    assert_eq!(eval("(map + (list 1 2 3) (list 10 20))"), "(11 22)");
+    assert_eq!(eval("(map first (list '(a b) '(c d)))"), "(a c)");
+    assert_eq!(eval("(filter (lambda (x) (> x 1)) (list 1 2 3))"), "(2 3)");
+    assert_eq!(eval("(filter symbol? '(a 1 b))"), "(a b)");
+    assert_eq!(eval("(fold (lambda (acc x) (list acc x)) 0 (list 1 2))"), "((0 1) 2)");
+    assert_eq!(eval("(fold + 0 '())"), "0");
+    assert_eq!(eval("(reduce + (list 1 2 3))"), "6");
+}
+
+#[test]
+#[should_panic(expected = "filter predicate expected bool, got: 1")]
+fn filter_non_bool() {
+    eval("(filter (lambda (x) x) (list 1 2))");
+}
+
+#[test]
+fn list_operations() {
+    assert_eq!(eval("(append (list 1) '() (list 2 3))"), "(1 2 3)");
+    assert_eq!(eval("(append)"), "()");
+    assert_eq!(eval("(reverse (list 1 2 3))"), "(3 2 1)");
+    assert_eq!(eval("(length (list 1 2 3))"), "3");
+    assert_eq!(eval("(length '())"), "0");
+    assert_eq!(eval("(nth (list 1 2 3) 1)"), "2");
+}
+
+#[test]
+#[should_panic(expected = "nth: index 3 out of bounds for list with len 3")]
+fn nth_out_of_bounds() {
+    eval("(nth (list 1 2 3) 3)");
+}
+
+#[test]
+fn sort() {
+    assert_eq!(eval("(sort (list 3 1 2))"), "(1 2 3)");
+    assert_eq!(eval(r#"(sort (list "b" "c" "a"))"#), r#"("a" "b" "c")"#);
+    assert_eq!(eval(r"(sort (list #\b #\a))"), r"(#\a #\b)");
+    assert_eq!(eval("(sort (list 3 1 2) >)"), "(3 2 1)");
+    assert_eq!(eval("(sort (list '(b 2) '(a 1)) (lambda (x y) (< (nth x 1) (nth y 1))))"), "((a 1) (b 2))");
+}
+
+#[test]
+#[should_panic(expected = "sort can't compare 1 and a, supply less?")]
+fn sort_mixed() {
+    eval("(sort (list 'a 1))");
+}
+
+#[test]
+fn string_basics() {
+    assert_eq!(eval(r#"(string-length "abc")"#), "3");
+    assert_eq!(eval(r#"(string-append "a" "bc" "")"#), r#""abc""#);
+    assert_eq!(eval(r#"(string-append)"#), r#""""#);
+    assert_eq!(eval(r#"(substring "hello" 1 3)"#), r#""el""#);
+    assert_eq!(eval(r#"(substring "hello" 2)"#), r#""llo""#);
+    assert_eq!(eval(r#"(string-ref "abc" 1)"#), r"#\b");
+    assert_eq!(eval(r#"(string-upcase "aBc")"#), r#""ABC""#);
+    assert_eq!(eval(r#"(string-downcase "aBc")"#), r#""abc""#);
+    assert_eq!(eval(r#"(string-trim "\s a b\t\n")"#), r#""a b""#);
+}
+
+#[test]
+#[should_panic(expected = "substring: invalid range 2..1 for string with len 5")]
+fn substring_invalid_range() {
+    eval(r#"(substring "hello" 2 1)"#);
+}
+
+#[test]
+fn string_search() {
+    assert_eq!(eval(r#"(string-split "a,b,,c" ",")"#), r#"("a" "b" "" "c")"#);
+    assert_eq!(eval(r#"(string-join (list "a" "b" "c") ", ")"#), r#""a, b, c""#);
+    assert_eq!(eval(r#"(string-join (list "a" "b"))"#), r#""ab""#);
+    assert_eq!(eval(r#"(string-index "hello" #\l)"#), "2");
+    assert_eq!(eval(r#"(string-index "hello" "lo")"#), "3");
+    assert_eq!(eval(r#"(string-index "hello" #\x)"#), "false");
+    assert_eq!(eval(r#"(string-contains? "hello" "ell")"#), "true");
+    assert_eq!(eval(r#"(string-contains? "hello" "x")"#), "false");
+}
+
+#[test]
+fn string_conversions_and_comparisons() {
+    assert_eq!(eval(r#"(string->list "ab")"#), r"(#\a #\b)");
+    assert_eq!(eval(r"(list->string (list #\a #\b))"), r#""ab""#);
+    assert_eq!(eval(r#"(string=? "a" "a" "a")"#), "true");
+    assert_eq!(eval(r#"(string<? "a" "b" "c")"#), "true");
+    assert_eq!(eval(r#"(string<? "a" "c" "b")"#), "false");
+    assert_eq!(eval(r#"(string>=? "b" "b" "a")"#), "true");
+}
+
+#[test]
+#[should_panic(expected = "string-length expected string, got: 1")]
+fn string_type_error() {
+    eval("(string-length 1)");
+}
+
+#[test]
+fn char_classification() {
+    assert_eq!(eval(r"(char-alphabetic? #\a)"), "true");
+    assert_eq!(eval(r"(char-alphabetic? (integer->char 228))"), "true");
+    assert_eq!(eval(r"(char-alphabetic? #\1)"), "false");
+    assert_eq!(eval(r"(char-numeric? #\7)"), "true");
+    assert_eq!(eval(r"(char-whitespace? #\\s)"), "true");
+    assert_eq!(eval(r"(char-whitespace? #\a)"), "false");
+    assert_eq!(eval(r"(char-upper-case? #\A)"), "true");
+    assert_eq!(eval(r"(char-lower-case? #\A)"), "false");
+}
+
+#[test]
+fn char_conversions_and_comparisons() {
+    assert_eq!(eval(r"(char->integer (char-upcase (integer->char 228)))"), "196");
+    assert_eq!(eval(r"(char-downcase #\A)"), r"#\a");
+    assert_eq!(eval(r"(char->integer (char-upcase (integer->char 223)))"), "223");
+    assert_eq!(eval(r"(digit-value #\7)"), "7");
+    assert_eq!(eval(r"(digit-value #\a)"), "false");
+    // arabic-indic 3, devanagari 9, superscript 2 and roman numeral 5
+    assert_eq!(eval("(map digit-value (map integer->char '(1635 2415 178 8548)))"), "(3 9 false false)");
+    assert_eq!(eval("(map char-numeric? (map integer->char '(1635 2415 178 8548)))"), "(true true false false)");
+    assert_eq!(eval(r"(char=? #\a #\a)"), "true");
+    assert_eq!(eval(r"(char<? #\a #\b #\c)"), "true");
+    assert_eq!(eval(r"(char>? #\b #\a #\b)"), "false");
+}
+
+#[test]
+#[should_panic(expected = "char-upcase expected char, got: ")]
+fn char_type_error() {
+    eval(r#"(char-upcase "a")"#);
+}
+
+// evaluates code and returns everything it wrote to the current output port
+fn output(code: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    let port = Port::output_string();
+    interpreter.set_current_output(port.clone());
+    let parsed = parse(code, interpreter.interner_mut()).unwrap();
+    for x in &parsed {
+        interpreter.evaluate_top_level(x);
+    }
+    port.get_output_string().unwrap()
+}
+
+#[test]
+fn display_and_write() {
+    assert_eq!(output(r#"(display "a\"b") (newline)"#), "a\"b\n");
+    assert_eq!(output(r#"(write "a\"b")"#), r#""a\"b""#);
+    assert_eq!(output(r"(display #\a) (write #\a)"), r"a#\a");
+    assert_eq!(output(r#"(display (list 1 "x" 'y)) (write (list 1 "x" 'y))"#), r#"(1 x y)(1 "x" y)"#);
+    assert_eq!(output(r#"(println "sum:" (+ 1 2))"#), "sum: 3\n");
+    assert_eq!(output("(println)"), "\n");
+    assert_eq!(eval(r#"(display "")"#), "()");
+}
+
+#[test]
+fn string_ports() {
+    assert_eq!(eval(r#"(let (p (open-input-string "ab")) (list (peek-char p) (read-char p) (read-char p) (read-char p)))"#),
+        r"(#\a #\a #\b [EOF])");
+    assert_eq!(eval(r#"(let (p (open-input-string "one\ntwo\n\nlast")) (list (read-line p) (read-line p) (read-line p) (read-line p) (read-line p)))"#),
+        r#"("one" "two" "" "last" [EOF])"#);
+    assert_eq!(eval(r#"(let (p (open-output-string)) (write-string "a" p) (write 'b p) (display "c" p) (newline p) (get-output-string p))"#),
+        r#""abc\n""#);
+    assert_eq!(eval(r#"(with-output-to-string (lambda () (display "x") (write-string "y")))"#), r#""xy""#);
+    assert_eq!(eval(r#"(list (input-port? (open-input-string "")) (output-port? (open-input-string "")) (eof-object? (read-char (open-input-string ""))))"#),
+        "(true false true)");
+    assert_eq!(output(r#"(with-output-to-string (lambda () (display "x"))) (display "y")"#), "y");
+}
+
+#[test]
+fn file_ports() {
+    let path = ::std::env::temp_dir().join("flip_file_ports_test.txt");
+    let path = path.to_str().unwrap();
+    let code = format!(r#"
+        (define out (open-output-file "{0}"))
+        (write-string "first line" out)
+        (newline out)
+        (display (list 1 2) out)
+        (close-port out)
+        (define in (open-input-file "{0}"))
+        (list (read-line in) (read-line in) (read-line in))"#, path);
+    assert_eq!(eval(&code), r#"("first line" "(1 2)" [EOF])"#);
+    ::std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "read-char: port string is closed")]
+fn closed_port() {
+    eval(r#"(let (p (open-input-string "ab")) (close-port p) (read-char p))"#);
+}
+
+#[test]
+fn read() {
+    assert_eq!(eval(r#"(read "(a (b \"c\")) d")"#), r#"(a (b "c"))"#);
+    assert_eq!(eval(r#"(let (p (open-input-string " 'x(y)#\\( `(,z) ")) (list (read p) (read p) (read p) (read p) (read p)))"#),
+        r"((quote x) (y) #\( (quasiquote ((unquote z))) [EOF])");
+    assert_eq!(eval(r#"(read-all "1 (+ 1 2) \"s\"")"#), r#"(1 (+ 1 2) "s")"#);
+    assert_eq!(eval(r#"(read-all "")"#), "()");
+    assert_eq!(eval(r##"(let (p (open-input-string "#\\) \"a)\\\"\" ,@x 'y")) (list (read p) (read p) (read p) (read p) (read p)))"##),
+        r#"(#\) "a)\"" (unquote-splicing x) (quote y) [EOF])"#);
+}
+
+#[test]
+#[should_panic(expected = "unexpected EOF")]
+fn read_incomplete() {
+    eval(r#"(read "(a (b)")"#);
+}
+
+#[test]
+fn eval_data() {
+    assert_eq!(eval(r#"(eval (read "(+ 1 2)"))"#), "3");
+    assert_eq!(eval("(eval '(if (< 1 2) (quote yes) (quote no)))"), "yes");
+    assert_eq!(eval("(eval (list 'let (list 'x 2) (list '* 'x 'x)))"), "4");
+    assert_eq!(eval("(eval '(define f (lambda (x) (+ x 1)))) (f 1)"), "2");
+    assert_eq!(eval("(defmacro twice (x) `(list ,x ,x)) (eval '(twice 1))"), "(1 1)");
+    assert_eq!(eval(r#"(eval "s")"#), r#""s""#);
+}
+
+#[test]
+fn eval_in_environment() {
+    assert_eq!(eval("(define env ((lambda (x) (current-environment)) 42)) (eval 'x env)"), "42");
+    assert_eq!(eval("(define env ((lambda () (current-environment)))) (eval '(define y 1) env) (eval 'y env)"), "1");
+}
+
+#[test]
+#[should_panic(expected = "undefined ident: y")]
+fn eval_define_in_environment_is_local() {
+    eval("(define env ((lambda () (current-environment)))) (eval '(define y 1) env) y");
+}
+
+// writes files into a fresh temporary directory and returns its path
+fn temp_files(name: &str, files: &[(&str, &str)]) -> ::std::path::PathBuf {
+    let dir = ::std::env::temp_dir().join(name);
+    let _ = ::std::fs::remove_dir_all(&dir);
+    ::std::fs::create_dir_all(dir.join("lib")).unwrap();
+    for &(file, content) in files {
+        ::std::fs::File::create(dir.join(file)).unwrap().write_all(content.as_bytes()).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn load_and_include() {
+    let dir = temp_files("flip_load_test", &[
+        ("main.flip", r#"(load "lib/a.flip") (include "lib/b.flip") (list (a) b (c))"#),
+        ("lib/a.flip", r#"(define a (lambda () 'a))"#),
+        ("lib/b.flip", r#"(define b 'b) (include "c.flip")"#),
+        ("lib/c.flip", r#"(define c (lambda () (include "d.flip")))"#),
+        ("lib/d.flip", r#"'c"#),
+    ]);
+    let code = format!(r#"(load "{}")"#, dir.join("main.flip").display());
+    assert_eq!(eval(&code), "(a b c)");
+
+    // macros of an included file can be used in its following forms
+    let dir = temp_files("flip_include_macro_test", &[("inc.flip", "(defmacro inc (x) `(+ ,x 1)) (define y (inc 1))")]);
+    assert_eq!(eval(&format!("(include {:?}) (list y (inc y))", dir.join("inc.flip").display().to_string())), "(2 3)");
+}
+
+#[test]
+#[should_panic(expected = "cyclic load:")]
+fn cyclic_load() {
+    let dir = temp_files("flip_cyclic_load_test", &[
+        ("a.flip", r#"(load "b.flip")"#),
+        ("b.flip", r#"(load "a.flip")"#),
+    ]);
+    eval(&format!(r#"(load "{}")"#, dir.join("a.flip").display()));
+}
+
+#[test]
+#[should_panic(expected = "bad.flip:")]
+fn load_parse_error() {
+    let dir = temp_files("flip_load_parse_error_test", &[("bad.flip", "(define x")]);
+    eval(&format!(r#"(load "{}")"#, dir.join("bad.flip").display()));
+}
+
+#[test]
+fn modules() {
+    let module = "(module str (export join twice) (define sep \", \") (define join (lambda (l) (string-join l sep))) (defmacro twice (x) `(list ,x ,x)))";
+    assert_eq!(eval(&format!("{} (import (str) :only ()) (str/join (list \"a\" \"b\"))", module)), r#""a, b""#);
+    assert_eq!(eval(&format!("{} (import (str)) (list (join (list \"a\")) (twice 1))", module)), r#"("a" (1 1))"#);
+    assert_eq!(eval(&format!("{} (import (str) :only (join) :prefix s-) (s-join (list \"a\" \"b\"))", module)), r#""a, b""#);
+    assert_eq!(eval(&format!("{} (import (str) :rename ((join j))) (j (list \"a\" \"b\"))", module)), r#""a, b""#);
+    // modules don't see the definitions around them
+    assert_eq!(eval("(define x 1) (module m (export x) (define x 2)) (import (m) :prefix m-) (list x m/x)"), "(1 2)");
+    // qualified names need an import, in the scope where they're used
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, &format!("{} str/join", module)), "error: undefined ident: str/join");
+    assert_eq!(eval_in(&mut interpreter, "((lambda () (import (str) :only ()) str/join))"), "[PROC: (lambda (l) (string-join l sep))]");
+    assert_eq!(eval_in(&mut interpreter, "str/join"), "error: undefined ident: str/join");
+    // local bindings shadow module and import
+    assert_eq!(eval("(let (module list import inc) (list (module 1) (import 1)))"), "((1) 2)");
+}
+
+#[test]
+#[should_panic(expected = "module m: exported car isn't defined")]
+fn export_builtin() {
+    eval("(module m (export car))");
+}
+
+#[test]
+#[should_panic(expected = "undefined ident: str/sep")]
+fn module_private_definitions() {
+    eval("(module str (export join) (define sep \"\") (define join (lambda (l) (string-join l sep)))) (import (str)) str/sep");
+}
+
+#[test]
+#[should_panic(expected = "import: module m doesn't export y")]
+fn import_not_exported() {
+    eval("(module m (export x) (define x 1) (define y 2)) (import (m) :only (y))");
+}
+
+#[test]
+#[should_panic(expected = "module m: exported y isn't defined")]
+fn export_undefined() {
+    eval("(module m (export y))");
+}
+
+#[test]
+fn module_files() {
+    let dir = temp_files("flip_module_files_test", &[
+        ("main.flip", r#"(import (greet)) (hello "you")"#),
+        ("greet.flip", r#"(module greet (export hello) (define hello (lambda (name) (string-append "hello " name))))"#),
+    ]);
+    let code = format!(r#"(load "{}")"#, dir.join("main.flip").display());
+    assert_eq!(eval(&code), r#""hello you""#);
+}
+
+#[test]
+fn arithmetic_errors() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, "(quotient 1 0)"), "error: quotient: division by zero");
+    assert_eq!(eval_in(&mut interpreter, "(remainder 1 0)"), "error: remainder: division by zero");
+    assert_eq!(eval_in(&mut interpreter, "(+ 9223372036854775807 1)"), "error: +: integer overflow");
+    assert_eq!(eval_in(&mut interpreter, "(* 9223372036854775807 2)"), "error: *: integer overflow");
+    assert_eq!(eval_in(&mut interpreter, "(- -9223372036854775807 2)"), "error: -: integer overflow");
+    assert_eq!(eval_in(&mut interpreter, "(quotient (- -9223372036854775807 1) -1)"), "error: quotient: integer overflow");
+    assert_eq!(eval_in(&mut interpreter, "(list (quotient 7 2) (remainder -7 2))"), "(3 -1)");
+    assert_eq!(eval_in(&mut interpreter, "(- (- -9223372036854775807 1))"), "error: -: integer overflow");
+    assert_eq!(eval_in(&mut interpreter, "(quotient 5)"), "error: arity mismatch for quotient: expected: 2, got: 1");
+    assert_eq!(eval_in(&mut interpreter, "(remainder 5 2 1)"), "error: arity mismatch for remainder: expected: 2, got: 3");
+}
+
+#[test]
+fn prelude() {
+    assert_eq!(eval("(list (not false) (identity 1) ((constantly 2) 3 4))"), "(true 1 2)");
+    assert_eq!(eval("(list ((comp inc (partial * 2)) 5) ((partial + 1 2) 3))"), "(11 6)");
+    assert_eq!(eval("(list (zero? 0) (abs -3) (even? 4) (odd? 4) (max 1 3 2) (min 3 1 2) (sum (range 5)))"),
+        "(true 3 true false 3 1 10)");
+    assert_eq!(eval("(list (sum '(7)) (sum '()) (+ 5) (- 5) (* 5) (+) (*))"), "(7 0 5 -5 5 0 1)");
+    assert_eq!(eval("(list (cons 1 '(2)) (second '(1 2)) (last '(1 2 3)) (take 2 '(1 2 3)) (drop 2 '(1 2 3)) (take 5 '(1)))"),
+        "((1 2) 2 3 (1 2) (3) (1))");
+    assert_eq!(eval("(list (cons 1 '()) (take -1 '(1)) (drop 5 '(1)) (range 3 1) (length (range 100000)))"), "((1) () () () 100000)");
+    assert_eq!(eval("(list (range 2 5) (any? even? '(1 2)) (every? even? '(2 3)) (zip '(1 2) '(a b)))"),
+        "((2 3 4) true false ((1 a) (2 b)))");
+    // modules see the prelude too
+    assert_eq!(eval("(module m (export f) (define f (lambda (x) (inc x)))) (import (m)) (f 1)"), "2");
+}
+
+#[test]
+#[should_panic(expected = "undefined ident: inc")]
+fn without_prelude() {
+    let mut interpreter = Interpreter::without_prelude();
+    let parsed = parse("(inc 1)", interpreter.interner_mut()).unwrap();
+    interpreter.evaluate_top_level(&parsed[0]);
+}
+
+#[test]
+fn eval_str() {
+    use ::error::Error;
+
+    let mut interpreter = Interpreter::new();
+    interpreter.register_native("answer", Arity::Exact(0), |_, _| Ok(Value::new_integer(42)));
+    let res = interpreter.eval_str("(define x (answer)) (+ x 1)").unwrap();
+    assert_eq!(res.to_string(interpreter.interner()), "43");
+    assert_eq!(interpreter.eval_str("").unwrap(), Value::empty_list());
+
+    match interpreter.eval_str("(define x") {
+        Err(Error::Parse(msg)) => assert!(msg.contains("unexpected EOF")),
+        res => panic!("expected parse error, got: {:?}", res),
+    }
+
+    // conditions raised in a procedure don't leave its scope behind
+    assert_eq!(interpreter.eval_str("((lambda (y) (undefined y)) 1)"), Err(Error::Condition("undefined ident: undefined".to_string())));
+    assert_eq!(interpreter.eval_str("x").unwrap().to_string(interpreter.interner()), "42");
+    assert!(interpreter.eval_str("y").is_err());
+}
+
+#[test]
+fn native_closures() {
+    use std::rc::Rc;
+    use std::cell::Cell;
+
+    let mut interpreter = Interpreter::new();
+    let counter = Rc::new(Cell::new(0));
+    let captured = counter.clone();
+    interpreter.register_native("count!", Arity::Range(0, 1), move |_, args| {
+        let step = match args.get(0) {
+            Some(step) => step.get_integer().ok_or(Value::new_string("count! expected integer"))?,
+            None => 1,
+        };
+        captured.set(captured.get() + step);
+        Ok(Value::new_integer(captured.get()))
+    });
+
+    let res = interpreter.eval_str("(count!) (count! (+ 1 1)) (map (lambda (x) (count!)) '(a b))").unwrap();
+    assert_eq!(res.to_string(interpreter.interner()), "(4 5)");
+    assert_eq!(counter.get(), 5);
+    assert_eq!(interpreter.eval_str("count!").unwrap().to_string(interpreter.interner()), "[NATIVE_PROC: count!]");
+    assert_eq!(interpreter.eval_str("first").unwrap().to_string(interpreter.interner()), "[NATIVE_PROC: first]");
+    assert_eq!(interpreter.eval_str("(count! 1 2)").unwrap_err().to_string(), "error: arity mismatch for count!: expected: 0..1, got: 2");
+    assert_eq!(interpreter.eval_str("(count! 'a)").unwrap_err().to_string(), "error: count! expected integer");
+    assert_eq!(interpreter.eval_str("(procedure? count!)").unwrap(), Value::new_bool(true));
+}
+
+#[test]
+fn call() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define add (lambda (a b) (+ a b))) (module m (export twice) (define twice (lambda (x) (list x x)))) (import (m) :only ())").unwrap();
+
+    let res = interpreter.call("add", (1, 2));
+    assert_eq!(show(&interpreter, res), "3");
+    let res = interpreter.call("m/twice", ("a",));
+    assert_eq!(show(&interpreter, res), r#"("a" "a")"#);
+    let res = interpreter.call("length", (vec![true, false],));
+    assert_eq!(show(&interpreter, res), "2");
+
+    // args are not evaluated again
+    let code = interpreter.eval_str("'(+ 1 2)").unwrap();
+    let res = interpreter.call("first", (code.clone(),));
+    assert_eq!(show(&interpreter, res), "+");
+    let res = interpreter.call("twice", (code.clone(),));
+    assert_eq!(show(&interpreter, res), "error: undefined ident: twice");
+    let twice = interpreter.eval_str("m/twice").unwrap();
+    let res = interpreter.call(&twice, vec![code]);
+    assert_eq!(show(&interpreter, res), "((+ 1 2) (+ 1 2))");
+    let inc = interpreter.eval_str("inc").unwrap();
+    let res = interpreter.call("map", (inc, vec![1, 2]));
+    assert_eq!(show(&interpreter, res), "(2 3)");
+
+    let res = interpreter.call("add", (1, "2"));
+    assert_eq!(show(&interpreter, res), r#"error: expected integer, got: "2""#);
+    let res = interpreter.call(Value::new_integer(1), ());
+    assert_eq!(show(&interpreter, res), "error: tried to call 1, which is not possible");
+    let res = interpreter.call("import", vec![Value::empty_list()]);
+    assert_eq!(show(&interpreter, res), "error: undefined ident: import");
+    assert_eq!(interpreter.eval_str("(add 1 2)").unwrap(), Value::new_integer(3));
+}
+
+#[test]
+fn host_objects() {
+    use std::cell::RefCell;
+    use ::value::HostObject;
+
+    struct Connection {
+        sent: RefCell<Vec<String>>,
+    }
+
+    let mut interpreter = Interpreter::new();
+    let a = HostObject::new("Connection", Connection { sent: RefCell::new(vec![]) });
+    let b = HostObject::new("Connection", Connection { sent: RefCell::new(vec![]) });
+    interpreter.define("a", Value::new_host_object(a.clone()));
+    interpreter.define("b", Value::new_host_object(b));
+    interpreter.define("number", Value::new_host_object(HostObject::new("Number", 1)));
+    interpreter.register_fn("send", |conn: HostObject, msg: String| match conn.downcast::<Connection>() {
+        Some(conn) => Ok(conn.sent.borrow_mut().push(msg)),
+        None => Err(format!("send expected Connection, got: {}", conn.to_string())),
+    });
+
+    assert_eq!(eval_in(&mut interpreter, "(list a number)"), "(#<host Connection> #<host Number>)");
+    assert_eq!(eval_in(&mut interpreter, "(list (eq? a a) (eq? a b) (eq? (first (list a)) a))"), "(true false true)");
+    assert_eq!(eval_in(&mut interpreter, r#"(map (lambda (conn) (send conn "hi")) (list a a))"#), "(() ())");
+    assert_eq!(eval_in(&mut interpreter, r#"(send number "hi")"#), "error: send expected Connection, got: #<host Number>");
+    assert_eq!(eval_in(&mut interpreter, r#"(send "a" "hi")"#), r#"error: send expected host object, got: "a""#);
+    assert_eq!(*a.downcast::<Connection>().unwrap().sent.borrow(), vec!["hi", "hi"]);
+    assert!(a.downcast::<i64>().is_none());
+}
+
+#[test]
+fn sandboxed() {
+    use ::interpreter::Capability;
+
+    let dir = temp_files("flip_sandbox_test", &[("m.flip", "(module m (export x) (define x 1))")]);
+    let path = dir.join("m.flip").display().to_string();
+
+    let mut interpreter = Interpreter::sandboxed();
+    assert_eq!(eval_in(&mut interpreter, &format!("(load {:?})", path)), "error: undefined ident: load");
+    assert_eq!(eval_in(&mut interpreter, &format!("(open-input-file {:?})", path)), "error: undefined ident: open-input-file");
+    assert_eq!(eval_in(&mut interpreter, &format!("(include {:?})", path)), format!("error: couldn't open {}: no access to the file system", path));
+    assert!(eval_in(&mut interpreter, "(import (m))").contains("no access to the file system"));
+    assert_eq!(eval_in(&mut interpreter, "(module n (export y) (define y 2)) (import (n)) (println (inc y))"), "()");
+    assert_eq!(eval_in(&mut interpreter, "(list (read-line) (current-input-port))"), "([EOF] [INPUT_PORT: string])");
+    assert_eq!(interpreter.current_output().get_port().unwrap().get_output_string().unwrap(), "3\n");
+    assert!(!interpreter.has_capability(Capability::Console));
+    // the size of values and the number of evaluation steps are limited by default
+    assert_eq!(eval_in(&mut interpreter, "(length (range 10000000000))"), "error: size limit exceeded: list with 10000000000 elements, the limit is 1000000");
+    assert_eq!(interpreter.limits().fuel.map(|fuel| fuel > 0), Some(true));
+    // unbounded recursion raises a condition instead of overflowing the stack
+    assert_eq!(eval_in(&mut interpreter, "(define f (lambda (x) (+ 1 (f x)))) (f 1)"), "error: depth limit exceeded: more than 500 nested evaluations");
+    assert_eq!(eval_in(&mut interpreter, "(define g (lambda (n) (if (= n 0) 0 (+ 1 (g (- n 1)))))) (g 100)"), "100");
+
+    let mut interpreter = Interpreter::with_capabilities(&[Capability::FileSystem]);
+    assert_eq!(eval_in(&mut interpreter, &format!("(load {:?}) (import (m)) x", path)), "1");
+    assert_eq!(eval_in(&mut interpreter, "(current-output-port)"), "[OUTPUT_PORT: string]");
+    assert_eq!(eval_in(&mut Interpreter::new(), "(current-output-port)"), "[OUTPUT_PORT: stdout]");
+}
+
+#[test]
+fn limits() {
+    use std::time::Duration;
+
+    let mut interpreter = Interpreter::new();
+    interpreter.limits_mut().fuel = Some(1000);
+    assert_eq!(eval_in(&mut interpreter, "(+ 1 2)"), "3");
+    assert_eq!(interpreter.limits().fuel, Some(996));
+    assert_eq!(eval_in(&mut interpreter, "(loop () (recur))"), "error: out of fuel");
+    assert_eq!(eval_in(&mut interpreter, "1"), "error: out of fuel");
+    interpreter.limits_mut().fuel = None;
+    assert_eq!(eval_in(&mut interpreter, "1"), "1");
+
+    interpreter.set_timeout(Duration::from_millis(50));
+    assert_eq!(eval_in(&mut interpreter, "(loop (i 0) (recur (inc i)))"), "error: timeout: the deadline for evaluation passed");
+    // the timeout is a budget for every eval
+    ::std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(eval_in(&mut interpreter, "1"), "1");
+    interpreter.limits_mut().timeout = None;
+
+    interpreter.limits_mut().max_size = Some(10);
+    assert_eq!(eval_in(&mut interpreter, "(length (range 10))"), "10");
+    assert_eq!(eval_in(&mut interpreter, "(range 11)"), "error: size limit exceeded: list with 11 elements, the limit is 10");
+    assert_eq!(eval_in(&mut interpreter, r#"(loop (s "a") (recur (string-append s s)))"#), "error: size limit exceeded: string with 16 bytes, the limit is 10");
+    assert_eq!(eval_in(&mut interpreter, r#""12345678901""#), "error: size limit exceeded: string with 11 bytes, the limit is 10");
+    // natives check the size before allocating
+    assert_eq!(eval_in(&mut interpreter, "(range 10000000000)"), "error: size limit exceeded: list with 10000000000 elements, the limit is 10");
+    assert_eq!(eval_in(&mut interpreter, "(append (range 6) (range 6))"), "error: size limit exceeded: list with 12 elements, the limit is 10");
+    assert_eq!(eval_in(&mut interpreter, r#"(string-append "123456" "123456")"#), "error: size limit exceeded: string with 12 bytes, the limit is 10");
+    assert_eq!(eval_in(&mut interpreter, r#"(string-join (list "12345" "12345") ", ")"#), "error: size limit exceeded: string with 12 bytes, the limit is 10");
+    interpreter.limits_mut().max_size = None;
+
+    interpreter.limits_mut().max_depth = Some(10);
+    assert_eq!(eval_in(&mut interpreter, "(+ 1 (+ 2 3))"), "6");
+    assert_eq!(eval_in(&mut interpreter, "(loop (i 0) (if (= i 100) i (recur (inc i))))"), "100");
+    assert_eq!(eval_in(&mut interpreter, "(define f (lambda (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))) (f 10)"), "error: depth limit exceeded: more than 10 nested evaluations");
+}
+
+#[test]
+fn interrupt() {
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut interpreter = Interpreter::new();
+    let interrupt = interpreter.limits().interrupt.clone();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        interrupt.store(true, Ordering::SeqCst);
+    });
+    let res = interpreter.eval_str("(define x 1) (loop (i 0) (recur (inc i)))");
+    interrupter.join().unwrap();
+    assert_eq!(res.unwrap_err().to_string(), "error: interrupted");
+    assert_eq!(interpreter.eval_str("x").unwrap(), Value::new_integer(1));
+}
+
+#[test]
+fn gc() {
+    let mut interpreter = Interpreter::new();
+
+    // every call of f leaves a cycle behind: g is defined in the scope of the call and references it
+    eval_in(&mut interpreter, "(define f (lambda (x) (define g (lambda () x)) g))");
+    eval_in(&mut interpreter, "(gc)");
+    let live = interpreter.gc_stats().live;
+    eval_in(&mut interpreter, "(loop (i 0) (if (< i 100) (begin (f i) (recur (inc i))) i))");
+    assert!(interpreter.gc_stats().live >= live + 100);
+
+    eval_in(&mut interpreter, "(define kept (f 'kept))");
+    let held = interpreter.eval_str("(f 'held)").unwrap();
+    let env = interpreter.eval_str("(let (y 'env) (current-environment))").unwrap();
+    let freed = interpreter.eval_str("(gc)").unwrap().get_integer().unwrap();
+    assert!(freed >= 100);
+    assert!(interpreter.gc_stats().live <= live + 10);
+
+    // everything which is still referenced keeps working
+    assert_eq!(eval_in(&mut interpreter, "(list (kept) (f 1) ((f 2)))"), "(kept [PROC: (lambda () x)] 2)");
+    assert_eq!(interpreter.call(&held, ()).unwrap().to_string(interpreter.interner()), "held");
+    let y = interpreter.eval_str("'y").unwrap();
+    assert_eq!(interpreter.call("eval", (y, env)).unwrap().to_string(interpreter.interner()), "env");
+    assert_eq!(eval_in(&mut interpreter, "(gc-stats)"), format!("((collections 2) (freed {}) (live {}))", freed, interpreter.gc_stats().live));
+}
@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Write};
+use std::rc::Rc;
+use std::str;
+
+// A port is a source of chars or a sink for strings. Ports are shared, so reading from
+// a port through one value advances it for all others.
+#[derive(Clone)]
+pub struct Port {
+    // eg. stdin or the path of a file
+    name: Rc<String>,
+    input: bool,
+    state: Rc<RefCell<State>>,
+}
+
+enum State {
+    // the peeked char has already been taken from the reader
+    Reader(Box<BufRead>, Option<char>),
+    // stdout and stderr are flushed after every write, files only when they are closed
+    Writer(Box<Write>, bool),
+    String(String),
+    Closed,
+}
+
+impl Port {
+    fn new(name: &str, input: bool, state: State) -> Self {
+        Port {
+            name: Rc::new(name.to_string()),
+            input: input,
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Port::new("stdin", true, State::Reader(Box::new(BufReader::new(io::stdin())), None))
+    }
+
+    pub fn stdout() -> Self {
+        Port::new("stdout", false, State::Writer(Box::new(io::stdout()), true))
+    }
+
+    pub fn stderr() -> Self {
+        Port::new("stderr", false, State::Writer(Box::new(io::stderr()), true))
+    }
+
+    pub fn open_input_file(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Port::new(path, true, State::Reader(Box::new(BufReader::new(file)), None)))
+    }
+
+    pub fn open_output_file(path: &str) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Port::new(path, false, State::Writer(Box::new(BufWriter::new(file)), false)))
+    }
+
+    pub fn input_string(s: &str) -> Self {
+        Port::new("string", true, State::Reader(Box::new(Cursor::new(s.as_bytes().to_vec())), None))
+    }
+
+    pub fn output_string() -> Self {
+        Port::new("string", false, State::String(String::new()))
+    }
+
+    pub fn is_input(&self) -> bool {
+        self.input
+    }
+
+    pub fn is_output(&self) -> bool {
+        !self.input
+    }
+
+    // returns None at the end of the input
+    pub fn read_char(&self) -> Result<Option<char>, String> {
+        match &mut *self.state.borrow_mut() {
+            &mut State::Reader(ref mut reader, ref mut peeked) => match peeked.take() {
+                Some(c) => Ok(Some(c)),
+                None => read_utf8_char(&mut **reader),
+            },
+            &mut State::Closed => Err(format!("port {} is closed", self.name)),
+            _ => Err(format!("port {} is no input port", self.name)),
+        }
+    }
+
+    pub fn peek_char(&self) -> Result<Option<char>, String> {
+        match &mut *self.state.borrow_mut() {
+            &mut State::Reader(ref mut reader, ref mut peeked) => {
+                if peeked.is_none() {
+                    *peeked = read_utf8_char(&mut **reader)?;
+                }
+                Ok(*peeked)
+            },
+            &mut State::Closed => Err(format!("port {} is closed", self.name)),
+            _ => Err(format!("port {} is no input port", self.name)),
+        }
+    }
+
+    // reads up to the next newline, which isn't part of the result, returns None at the end of the input
+    pub fn read_line(&self) -> Result<Option<String>, String> {
+        match &mut *self.state.borrow_mut() {
+            &mut State::Reader(ref mut reader, ref mut peeked) => {
+                let mut line = String::new();
+                match peeked.take() {
+                    Some('\n') => return Ok(Some(line)),
+                    Some(c) => line.push(c),
+                    None => (),
+                }
+
+                let read = reader.read_line(&mut line).map_err(|err| err.to_string())?;
+                if read == 0 && line.is_empty() {
+                    return Ok(None);
+                }
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') { line.pop(); }
+                }
+                Ok(Some(line))
+            },
+            &mut State::Closed => Err(format!("port {} is closed", self.name)),
+            _ => Err(format!("port {} is no input port", self.name)),
+        }
+    }
+
+    // reads everything up to the end of the input
+    pub fn read_to_string(&self) -> Result<String, String> {
+        match &mut *self.state.borrow_mut() {
+            &mut State::Reader(ref mut reader, ref mut peeked) => {
+                let mut s: String = peeked.take().into_iter().collect();
+                reader.read_to_string(&mut s).map_err(|err| err.to_string())?;
+                Ok(s)
+            },
+            &mut State::Closed => Err(format!("port {} is closed", self.name)),
+            _ => Err(format!("port {} is no input port", self.name)),
+        }
+    }
+
+    pub fn write_str(&self, s: &str) -> Result<(), String> {
+        match &mut *self.state.borrow_mut() {
+            &mut State::Writer(ref mut writer, autoflush) => {
+                writer.write_all(s.as_bytes()).map_err(|err| err.to_string())?;
+                if autoflush {
+                    writer.flush().map_err(|err| err.to_string())?;
+                }
+                Ok(())
+            },
+            &mut State::String(ref mut buffer) => {
+                buffer.push_str(s);
+                Ok(())
+            },
+            &mut State::Closed => Err(format!("port {} is closed", self.name)),
+            &mut State::Reader(..) => Err(format!("port {} is no output port", self.name)),
+        }
+    }
+
+    // everything written to an output string port so far
+    pub fn get_output_string(&self) -> Option<String> {
+        match &*self.state.borrow() {
+            &State::String(ref buffer) => Some(buffer.clone()),
+            _ => None,
+        }
+    }
+
+    // closing a closed port does nothing
+    pub fn close(&self) -> Result<(), String> {
+        let mut state = self.state.borrow_mut();
+        if let &mut State::Writer(ref mut writer, _) = &mut *state {
+            writer.flush().map_err(|err| err.to_string())?;
+        }
+        *state = State::Closed;
+        Ok(())
+    }
+
+    pub fn to_string(&self) -> String {
+        let direction = if self.input { "INPUT_PORT" } else { "OUTPUT_PORT" };
+        format!("[{}: {}]", direction, self.name)
+    }
+}
+
+impl fmt::Debug for Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+// ports are only equal to themselves
+impl PartialEq for Port {
+    fn eq(&self, other: &Port) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+fn read_utf8_char(reader: &mut BufRead) -> Result<Option<char>, String> {
+    let mut bytes = [0; 4];
+    if reader.read(&mut bytes[..1]).map_err(|err| err.to_string())? == 0 {
+        return Ok(None);
+    }
+
+    let len = match bytes[0] {
+        x if x < 0x80 => 1,
+        x if x >> 5 == 0b110 => 2,
+        x if x >> 4 == 0b1110 => 3,
+        x if x >> 3 == 0b11110 => 4,
+        _ => return Err("invalid UTF-8 in input".to_string()),
+    };
+    reader.read_exact(&mut bytes[1..len]).map_err(|err| err.to_string())?;
+
+    match str::from_utf8(&bytes[..len]) {
+        Ok(s) => Ok(s.chars().next()),
+        Err(_) => Err("invalid UTF-8 in input".to_string()),
+    }
+}
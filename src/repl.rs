@@ -1,26 +1,27 @@
 use rustyline;
 use std::iter;
+use std::sync::atomic::Ordering;
 use std::collections::btree_set::BTreeSet;
-use ::interpreter;
-use ::grammar::{self, error_printing};
-use ::value::Value;
+use flip::Interpreter;
 
 pub struct Repl {}
 
 impl Repl {
-    pub fn start() {
+    pub fn start(mut interpreter: Interpreter) {
         let quit = "(quit)";
         let break_chars: BTreeSet<char> = vec![' ', '(', '\''].into_iter().collect();
         let mut rl = rustyline::Editor::<IdentCompleter>::new();
         rl.add_history_entry(quit);
 
-        let mut interpreter = interpreter::Interpreter::new();
+        // Ctrl-C while a form is evaluated aborts it with a condition, at the prompt rustyline gets it instead
+        let interrupt = interpreter.limits().interrupt.clone();
+        let handler_interrupt = interrupt.clone();
+        if let Err(err) = ctrlc::set_handler(move || handler_interrupt.store(true, Ordering::SeqCst)) {
+            println!("Ctrl-C can't interrupt evaluation: {}", err);
+        }
 
         loop {
-            let idents: Vec<String> = interpreter.current_scope.symbol_ids()
-            .into_iter()
-            .filter_map(|id| interpreter.interner.lookup(id).map(Into::into))
-            .collect();
+            let idents = interpreter.defined_names();
 
             let completer = IdentCompleter {
                 break_chars: &break_chars,
@@ -29,22 +30,20 @@ impl Repl {
 
             rl.set_completer(Some(completer));
 
-            let line = rl.readline(">> ");
-            let line = if line.is_err() { return } else { line.unwrap() };
+            let line = match rl.readline(">> ") {
+                Ok(line) => line,
+                // Ctrl-C discards the line
+                Err(rustyline::error::ReadlineError::Interrupted) => continue,
+                Err(_) => return,
+            };
             if line == quit { return }
             rl.add_history_entry(&line);
 
-            // strip \n at the end
-            let parsed = grammar::parse(&line, &mut interpreter.interner);
-            match parsed {
-                Ok(values) => {
-                    let mut result = Value::empty_list();
-                    for x in &values {
-                        result = interpreter.evaluate(x);
-                    }
-                    println!("=> {}", result.to_string(&interpreter.interner))
-                },
-                Err(ref err)  => println!("{}", error_printing::create_error_message(&line, err)),
+            // conditions don't end the repl
+            interrupt.store(false, Ordering::SeqCst);
+            match interpreter.eval_str(&line) {
+                Ok(result) => println!("=> {}", result.to_string(interpreter.interner())),
+                Err(err) => println!("{}", err),
             }
         }
     }
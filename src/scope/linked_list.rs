@@ -11,11 +11,21 @@ pub struct List<T> {
 type Link<T> = Option<Rc<Node<T>>>;
 
 #[derive(Debug, Clone, PartialEq)]
-struct Node<T> {
+pub struct Node<T> {
     elem: T,
     next: Link<T>,
 }
 
+impl<T> Node<T> {
+    pub fn elem(&self) -> &T {
+        &self.elem
+    }
+
+    pub fn next(&self) -> Option<&Rc<Node<T>>> {
+        self.next.as_ref()
+    }
+}
+
 impl<T> List<T> {
     pub fn new() -> Self {
         List { list_head: None }
@@ -38,6 +48,10 @@ impl<T> List<T> {
     }
     */
 
+    pub fn head_node(&self) -> Option<&Rc<Node<T>>> {
+        self.list_head.as_ref()
+    }
+
     pub fn head(&self) -> Option<&T> {
         self.list_head.as_ref().map(|node| &node.elem)
     }
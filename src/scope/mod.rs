@@ -1,9 +1,45 @@
 mod linked_list;
 
-use std::collections::hash_map::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::cell::RefCell;
+use std::mem;
+use std::rc::{Rc, Weak};
 use ::value::Value;
-use self::linked_list::List;
+use self::linked_list::{List, Node};
+
+// the ScopeData of one scope together with the link to its parent, the cycle collector frees these
+pub type ScopeNode = Rc<Node<RefCell<ScopeData>>>;
+
+thread_local! {
+    // every node created on this thread, so the cycle collector can find them
+    static SCOPE_NODES: RefCell<Vec<Weak<Node<RefCell<ScopeData>>>>> = RefCell::new(vec![]);
+}
+
+fn register(scope: Scope) -> Scope {
+    SCOPE_NODES.with(|nodes| {
+        let mut nodes = nodes.borrow_mut();
+        // forget freed nodes before the vec grows
+        if nodes.len() == nodes.capacity() {
+            nodes.retain(|node| node.upgrade().is_some());
+        }
+        nodes.push(Rc::downgrade(scope.head_node()));
+    });
+    scope
+}
+
+// all nodes which haven't been freed yet
+pub fn live_nodes() -> Vec<ScopeNode> {
+    SCOPE_NODES.with(|nodes| {
+        let mut nodes = nodes.borrow_mut();
+        nodes.retain(|node| node.upgrade().is_some());
+        nodes.iter().filter_map(Weak::upgrade).collect()
+    })
+}
+
+// number of nodes which might not have been freed, without looking at them
+pub fn tracked_nodes() -> usize {
+    SCOPE_NODES.with(|nodes| nodes.borrow().len())
+}
 
 // The list holds ScopeData structs, which store the actual data (say bindings etc. etc.)
 // The front of the list is the lastly created ScopeData. The back is the last ScopeData that
@@ -19,17 +55,21 @@ impl Scope {
     }
 
     fn with_scope_data(scope_data: RefCell<ScopeData>) -> Self {
-        Scope {
+        register(Scope {
             list: List::new().append(scope_data),
-        }
+        })
     }
 
     // creates a child scope linked with it's parents
     pub fn new_child(&self) -> Self {
         let fresh_data = RefCell::new(ScopeData::new());
-        Scope {
+        register(Scope {
             list: self.list.append(fresh_data)
-        }
+        })
+    }
+
+    pub fn head_node(&self) -> &ScopeNode {
+        self.list.head_node().expect("internal error: scope without data")
     }
 
     pub fn lookup_symbol(&self, id: u64) -> Option<Value> {
@@ -43,11 +83,26 @@ impl Scope {
         None
     }
 
+    // only the bindings of this scope, not of its parents
+    pub fn lookup_local(&self, id: u64) -> Option<Value> {
+        self.list.head().and_then(|scope_data| scope_data.borrow().lookup_symbol(id).cloned())
+    }
+
     pub fn add_symbol(&mut self, id: u64, value: Value) {
         self.list.head().expect("internal error: scope without data")
         .borrow_mut().bindings.insert(id, value);
     }
 
+    // the modules imported into this scope or its parents can be referred to with qualified names
+    pub fn add_import(&mut self, module: u64) {
+        self.list.head().expect("internal error: scope without data")
+        .borrow_mut().imports.insert(module);
+    }
+
+    pub fn has_import(&self, module: u64) -> bool {
+        self.list.iter().any(|scope_data| scope_data.borrow().imports.contains(&module))
+    }
+
     pub fn symbol_ids<'a>(&'a self) -> Vec<u64> {
         let mut symbol_strings: Vec<u64> = vec![];
         for scope in self.list.iter().map(RefCell::borrow) {
@@ -62,12 +117,14 @@ impl Scope {
 #[derive(Debug, PartialEq)]
 pub struct ScopeData {
     bindings: HashMap<u64, Value>,
+    imports: HashSet<u64>,
 }
 
 impl ScopeData {
     fn new() -> Self {
         ScopeData {
             bindings: HashMap::new(),
+            imports: HashSet::new(),
         }
     }
 
@@ -75,6 +132,15 @@ impl ScopeData {
         self.bindings.get(&id)
     }
 
+    pub fn values<'a>(&'a self) -> impl Iterator<Item=&'a Value> + 'a {
+        self.bindings.values()
+    }
+
+    // used by the cycle collector to break cycles, the bindings are dropped by the caller
+    pub fn take_bindings(&mut self) -> HashMap<u64, Value> {
+        mem::replace(&mut self.bindings, HashMap::new())
+    }
+
     fn symbol_ids<'a>(&'a self) -> impl Iterator<Item=u64> + 'a {
         self.bindings.keys().map(|x| *x)
     }
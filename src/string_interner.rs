@@ -6,18 +6,17 @@ use siphasher::sip::SipHasher24 as SipHasher;
 
 pub struct StringInterner {
     map: HashMap<u64, String>,
+    gensym_counter: u64,
 }
 
 impl StringInterner {
     pub fn new() -> Self {
-        StringInterner { map: HashMap::new() }
+        StringInterner { map: HashMap::new(), gensym_counter: 0 }
     }
 
     pub fn intern<'a, T: 'a + Into<Cow<'a, str>>>(&mut self, s: T) -> u64 {
         let string = s.into();
-        let mut h = SipHasher::new();
-        string.hash(&mut h);
-        let id = h.finish();
+        let id = hash(&string);
         match self.map.entry(id) {
             Entry::Occupied(o) => debug_assert!(o.get() == &string),
             Entry::Vacant(o) => { o.insert(string.into_owned()); },
@@ -25,7 +24,26 @@ impl StringInterner {
         id
     }
 
+    // interns a fresh symbol, which is named after the symbol id but not equal to any other symbol
+    // its name contains a space, so the reader can't produce it
+    pub fn gensym(&mut self, id: u64) -> u64 {
+        let name = self.lookup(id).map(String::from).unwrap_or(id.to_string());
+        loop {
+            self.gensym_counter += 1;
+            let fresh = format!("#<{} {}>", name, self.gensym_counter);
+            if !self.map.contains_key(&hash(&fresh)) {
+                return self.intern(fresh);
+            }
+        }
+    }
+
     pub fn lookup(&self, id: u64) -> Option<&str> {
         self.map.get(&id).map(|x| &**x)
     }
 }
+
+fn hash(s: &str) -> u64 {
+    let mut h = SipHasher::new();
+    s.hash(&mut h);
+    h.finish()
+}
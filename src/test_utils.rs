@@ -0,0 +1,29 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use ::error::Error;
+use ::grammar::parse;
+
+// evaluates code in a fresh interpreter and returns the printed result of the last expression,
+// conditions panic
+pub fn eval(code: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    let parsed = parse(code, interpreter.interner_mut()).unwrap();
+    let mut result = Value::empty_list();
+    for x in &parsed {
+        result = interpreter.evaluate_top_level(x);
+    }
+    result.to_string(interpreter.interner())
+}
+
+// the printed result or error of eval_str
+pub fn eval_in(interpreter: &mut Interpreter, code: &str) -> String {
+    let res = interpreter.eval_str(code);
+    show(interpreter, res)
+}
+
+pub fn show(interpreter: &Interpreter, res: Result<Value, Error>) -> String {
+    match res {
+        Ok(x) => x.to_string(interpreter.interner()),
+        Err(err) => err.to_string(),
+    }
+}
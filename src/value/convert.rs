@@ -0,0 +1,240 @@
+use std::collections::{HashMap, BTreeMap};
+use std::fmt::Display;
+use std::hash::Hash;
+use ::value::{Value, NativeProc, HostObject, Arity};
+use ::interpreter::Interpreter;
+
+// Conversions between rust and flip values.
+// Option<T> is false or a T, maps are lists of (key value) pairs and tuples are lists of fixed length.
+pub trait FromValue<'a>: Sized {
+    // used in error messages, like the type names of try_unwrap_type!
+    fn type_name() -> String;
+    fn from_value(value: &'a Value) -> Option<Self>;
+}
+
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+macro_rules! primitive_conversion {
+    ($t:ty, $type_name:expr, $get_fn:path, $new_fn:path) => (
+        impl<'a> FromValue<'a> for $t {
+            fn type_name() -> String { $type_name.to_string() }
+            fn from_value(value: &'a Value) -> Option<Self> { $get_fn(value) }
+        }
+
+        impl IntoValue for $t {
+            fn into_value(self) -> Value { $new_fn(self) }
+        }
+    );
+}
+
+primitive_conversion!(i64, "integer", Value::get_integer, Value::new_integer);
+primitive_conversion!(bool, "bool", Value::get_bool, Value::new_bool);
+primitive_conversion!(char, "char", Value::get_char, Value::new_char);
+
+impl<'a> FromValue<'a> for Value {
+    fn type_name() -> String { "any value".to_string() }
+    fn from_value(value: &'a Value) -> Option<Self> { Some(value.clone()) }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value { self }
+}
+
+impl<'a> FromValue<'a> for HostObject {
+    fn type_name() -> String { "host object".to_string() }
+    fn from_value(value: &'a Value) -> Option<Self> { value.get_host_object().cloned() }
+}
+
+impl IntoValue for HostObject {
+    fn into_value(self) -> Value { Value::new_host_object(self) }
+}
+
+impl<'a> FromValue<'a> for String {
+    fn type_name() -> String { "string".to_string() }
+    fn from_value(value: &'a Value) -> Option<Self> { value.get_string().map(String::from) }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value { Value::new_string(self) }
+}
+
+impl<'a> FromValue<'a> for &'a str {
+    fn type_name() -> String { "string".to_string() }
+    fn from_value(value: &'a Value) -> Option<Self> { value.get_string() }
+}
+
+impl<'a> IntoValue for &'a str {
+    fn into_value(self) -> Value { Value::new_string(self) }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value { Value::empty_list() }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Option<T> {
+    fn type_name() -> String { format!("{} or false", T::type_name()) }
+    fn from_value(value: &'a Value) -> Option<Self> {
+        match value.get_bool() {
+            Some(false) => Some(None),
+            _ => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(x) => x.into_value(),
+            None => Value::new_bool(false),
+        }
+    }
+}
+
+// Err is turned into a condition
+impl<T: IntoValue, E: Display> IntoValue for Result<T, E> {
+    fn into_value(self) -> Value {
+        match self {
+            Ok(x) => x.into_value(),
+            Err(err) => new_condition!(err.to_string()),
+        }
+    }
+}
+
+// lists don't borrow their elements, so only types which own their data can be elements
+impl<'a, T: for<'b> FromValue<'b>> FromValue<'a> for Vec<T> {
+    fn type_name() -> String { format!("list of {}", T::type_name()) }
+    fn from_value(value: &'a Value) -> Option<Self> {
+        value.get_list().and_then(|list| list.iter().map(T::from_value).collect())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        let list: Vec<Value> = self.into_iter().map(IntoValue::into_value).collect();
+        Value::new_list(&list)
+    }
+}
+
+macro_rules! tuple_conversion {
+    ($len:expr; $($t:ident $i:tt $x:ident),+) => (
+        impl<'a, $($t: for<'b> FromValue<'b>),+> FromValue<'a> for ($($t,)+) {
+            fn type_name() -> String {
+                let types: Vec<String> = vec![$($t::type_name()),+];
+                format!("({})", types.join(" "))
+            }
+            fn from_value(value: &'a Value) -> Option<Self> {
+                let list = match value.get_list() {
+                    Some(ref list) if list.len() == $len => list.clone(),
+                    _ => return None,
+                };
+                match ($($t::from_value(&list[$i]),)+) {
+                    ($(Some($x),)+) => Some(($($x,)+)),
+                    _ => None,
+                }
+            }
+        }
+
+        impl<$($t: IntoValue),+> IntoValue for ($($t,)+) {
+            fn into_value(self) -> Value {
+                Value::new_list(&[$(self.$i.into_value()),+])
+            }
+        }
+
+        impl<$($t: IntoValue),+> IntoArgs for ($($t,)+) {
+            fn into_args(self) -> Vec<Value> {
+                vec![$(self.$i.into_value()),+]
+            }
+        }
+    );
+}
+
+tuple_conversion!(1; A 0 a);
+tuple_conversion!(2; A 0 a, B 1 b);
+tuple_conversion!(3; A 0 a, B 1 b, C 2 c);
+tuple_conversion!(4; A 0 a, B 1 b, C 2 c, D 3 d);
+
+// args of Interpreter::call, eg. (1, "a") or a Vec<Value>
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Value> { vec![] }
+}
+
+impl<T: IntoValue> IntoArgs for Vec<T> {
+    fn into_args(self) -> Vec<Value> { self.into_iter().map(IntoValue::into_value).collect() }
+}
+
+impl<'a> IntoArgs for &'a [Value] {
+    fn into_args(self) -> Vec<Value> { self.to_vec() }
+}
+
+macro_rules! map_conversion {
+    ($map:ident, $($bound:ident),+) => (
+        impl<'a, K, V> FromValue<'a> for $map<K, V>
+        where K: for<'b> FromValue<'b> $(+ $bound)+, V: for<'b> FromValue<'b> {
+            fn type_name() -> String { format!("list of ({} {})", K::type_name(), V::type_name()) }
+            fn from_value(value: &'a Value) -> Option<Self> {
+                Vec::<(K, V)>::from_value(value).map(|pairs| pairs.into_iter().collect())
+            }
+        }
+
+        impl<K: IntoValue, V: IntoValue> IntoValue for $map<K, V> {
+            fn into_value(self) -> Value {
+                self.into_iter().collect::<Vec<(K, V)>>().into_value()
+            }
+        }
+    );
+}
+
+map_conversion!(HashMap, Eq, Hash);
+map_conversion!(BTreeMap, Ord);
+
+// Rust fns, whose args and result can be converted, can be used as native procedures.
+// Arity and types are checked before the fn is called.
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> NativeProc;
+}
+
+fn convert_arg<'a, T: FromValue<'a>>(interpreter: &Interpreter, name: &str, value: &'a Value) -> Result<T, Value> {
+    match T::from_value(value) {
+        Some(x) => Ok(x),
+        None => Err(new_condition!(format!("{} expected {}, got: {}", name, T::type_name(), value.to_string(&interpreter.interner)))),
+    }
+}
+
+// marks a &str param in the Args of IntoNative, a &str borrows from the args, so it can't be a
+// type param of the impl like the owned args
+pub struct BorrowedStr;
+
+// generates an impl for every combination of owned and &str params:
+// the generics, the Args marker, the param types of the fn and the type each arg is converted to
+macro_rules! fn_conversion {
+    ($len:expr; [$($g:ident),*] [$($m:ty),*] [$($p:ty),*] [$($arg:ident: $t:ty),*];) => (
+        impl<F, R, $($g),*> IntoNative<($($m,)*)> for F
+        where F: for<'s> Fn($($p),*) -> R + 'static, R: IntoValue, $($g: for<'a> FromValue<'a>),* {
+            #[allow(unused_variables, unused_mut)]
+            fn into_native(self, name: &str) -> NativeProc {
+                let fn_name = name.to_string();
+                NativeProc::new(name, Arity::Exact($len), move |interpreter, args| {
+                    let mut args = args.iter();
+                    $(let $arg: $t = convert_arg(interpreter, &fn_name, args.next().unwrap())?;)*
+                    Ok(self($($arg),*).into_value())
+                })
+            }
+        }
+    );
+    ($len:expr; [$($g:ident),*] [$($m:ty),*] [$($p:ty),*] [$($arg:ident: $t:ty),*]; $next:ident $next_arg:ident $(, $rest:ident $rest_arg:ident)*) => (
+        fn_conversion!($len; [$($g,)* $next] [$($m,)* $next] [$($p,)* $next] [$($arg: $t,)* $next_arg: $next]; $($rest $rest_arg),*);
+        fn_conversion!($len; [$($g),*] [$($m,)* BorrowedStr] [$($p,)* &'s str] [$($arg: $t,)* $next_arg: &str]; $($rest $rest_arg),*);
+    );
+}
+
+fn_conversion!(0; [] [] [] [];);
+fn_conversion!(1; [] [] [] []; A a);
+fn_conversion!(2; [] [] [] []; A a, B b);
+fn_conversion!(3; [] [] [] []; A a, B b, C c);
+fn_conversion!(4; [] [] [] []; A a, B b, C c, D d);
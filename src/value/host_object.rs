@@ -0,0 +1,46 @@
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+// A rust object passed through flip code unchanged, eg. a connection of the embedding program.
+// Natives get their objects back with downcast.
+#[derive(Clone)]
+pub struct HostObject {
+    type_name: Rc<String>,
+    object: Rc<Any>,
+}
+
+impl HostObject {
+    pub fn new<T: Any>(type_name: &str, object: T) -> Self {
+        HostObject {
+            type_name: Rc::new(type_name.to_string()),
+            object: Rc::new(object),
+        }
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    // None if the object isn't a T
+    pub fn downcast<T: Any>(&self) -> Option<&T> {
+        self.object.downcast_ref::<T>()
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("#<host {}>", self.type_name)
+    }
+}
+
+impl fmt::Debug for HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+// host objects are only equal to themselves
+impl PartialEq for HostObject {
+    fn eq(&self, other: &HostObject) -> bool {
+        Rc::ptr_eq(&self.object, &other.object)
+    }
+}
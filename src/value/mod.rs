@@ -4,6 +4,21 @@ pub use self::value::*;
 mod procedure;
 pub use self::procedure::*;
 
+mod native_proc;
+pub use self::native_proc::*;
+
+mod host_object;
+pub use self::host_object::*;
+
+mod convert;
+pub use self::convert::*;
+
+mod pattern;
+pub use self::pattern::*;
+
+mod syntax_rules;
+pub use self::syntax_rules::*;
+
 
 mod special_forms;
 pub use self::special_forms::*;
@@ -0,0 +1,87 @@
+use std::fmt;
+use std::rc::Rc;
+use ::value::Value;
+use ::interpreter::Interpreter;
+
+// number of args a native procedure takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    // inclusive
+    Range(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn check(&self, name: &str, len: usize) -> Result<(), Value> {
+        let expected = match *self {
+            Arity::Exact(n) if len != n => format!("{}", n),
+            Arity::Range(lo, hi) if len < lo || len > hi => format!("{}..{}", lo, hi),
+            Arity::AtLeast(min) if len < min => format!("{}..", min),
+            _ => return Ok(()),
+        };
+        Err(new_condition!(format!("arity mismatch for {}: expected: {}, got: {}", name, expected, len)))
+    }
+}
+
+// conditions are values, which are created with Value::new_condition
+pub type Condition = Value;
+
+#[derive(Clone)]
+pub struct NativeProc {
+    name: Rc<String>,
+    arity: Arity,
+    // gets evaluated args, an Err which isn't a condition yet is turned into one
+    function: Rc<Fn(&mut Interpreter, &[Value]) -> Result<Value, Condition>>,
+}
+
+impl NativeProc {
+    // f can capture state, its arity is checked before it is called
+    pub fn new<F>(name: &str, arity: Arity, f: F) -> Self
+    where F: Fn(&mut Interpreter, &[Value]) -> Result<Value, Condition> + 'static {
+        NativeProc {
+            name: Rc::new(name.to_string()),
+            arity: arity,
+            function: Rc::new(f),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    // calls the procedure with unevaluated args
+    pub fn evaluate(&self, interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        let args: Vec<Value> = args.iter().map(|x| interpreter.evaluate(x)).collect();
+        self.apply(interpreter, &args)
+    }
+
+    // calls the procedure with already evaluated args
+    pub fn apply(&self, interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        if let Err(condition) = self.arity.check(&self.name, args.len()) {
+            return condition;
+        }
+        match (self.function)(interpreter, args) {
+            Ok(res) => res,
+            Err(ref condition) if condition.get_condition().is_some() => condition.clone(),
+            Err(value) => Value::new_condition(value),
+        }
+    }
+}
+
+impl fmt::Debug for NativeProc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeProc({})", self.name)
+    }
+}
+
+// natives are only equal to themselves
+impl PartialEq for NativeProc {
+    fn eq(&self, other: &NativeProc) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
+    }
+}
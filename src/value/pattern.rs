@@ -0,0 +1,158 @@
+use ::value::Value;
+use ::interpreter::Interpreter;
+use ::string_interner::StringInterner;
+use itertools::Itertools;
+
+// Patterns are used wherever names get bound: let, loop, lambda arguments, recur and match.
+// A symbol binds the whole value, a list pattern destructures a list element by element.
+// The optional rest pattern (written after &) is bound to a list of the remaining elements.
+// Wildcards, literals and predicates can only be used with match.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Symbol(u64),
+    Literal(Value),
+    List(Vec<Pattern>, Option<Box<Pattern>>),
+    // name of the predicate, pattern for the value if the predicate returned true
+    Predicate(u64, Box<Pattern>),
+}
+
+impl Pattern {
+    // creates a list pattern from patterns, where the pattern following a rest_marker is the rest pattern
+    // returns None if rest_marker isn't followed by exactly one pattern
+    pub fn new_list(mut patterns: Vec<Pattern>, rest_marker: &Pattern) -> Option<Self> {
+        match patterns.iter().position(|p| p == rest_marker) {
+            None => Some(Pattern::List(patterns, None)),
+            Some(pos) if pos + 2 == patterns.len() => {
+                let rest = patterns.pop().map(Box::new);
+                patterns.pop();
+                Some(Pattern::List(patterns, rest))
+            },
+            Some(_) => None,
+        }
+    }
+
+    // collects the bindings of value destructured with this pattern, returns false if value doesn't fit
+    pub fn destructure(&self, value: &Value, bindings: &mut Vec<(u64, Value)>, interpreter: &mut Interpreter) -> bool {
+        match self {
+            &Pattern::Wildcard => true,
+            &Pattern::Symbol(id) => {
+                bindings.push((id, value.clone()));
+                true
+            },
+            &Pattern::Literal(ref literal) => literal == value,
+            &Pattern::Predicate(predicate, ref pattern) => {
+                let predicate = Value::new_symbol(predicate);
+                let f = interpreter.evaluate(&predicate);
+                let res = interpreter.apply(&f, &[value.clone()]);
+                match res.get_bool() {
+                    Some(matches) => matches && pattern.destructure(value, bindings, interpreter),
+                    None => interpreter.raise(&new_condition!(format!("match predicate {} expected bool, found: {}",
+                        predicate.to_string(&interpreter.interner), res.to_string(&interpreter.interner)))),
+                }
+            },
+            &Pattern::List(ref patterns, ref rest) => {
+                let list = match value.get_list() {
+                    Some(list) => list,
+                    None => return false,
+                };
+
+                let fits = match rest {
+                    &Some(_) => list.len() >= patterns.len(),
+                    &None => list.len() == patterns.len(),
+                };
+                if !fits { return false; }
+
+                for (pattern, element) in patterns.iter().zip(list.iter()) {
+                    if !pattern.destructure(element, bindings, interpreter) { return false; }
+                }
+
+                match rest {
+                    &Some(ref rest) => rest.destructure(&Value::new_list(&list[patterns.len()..]), bindings, interpreter),
+                    &None => true,
+                }
+            },
+        }
+    }
+
+    // binds value to this pattern in the current scope, returns a condition if value doesn't fit
+    // form is the name of whatever is binding, eg. "let"
+    pub fn bind(&self, form: &str, value: &Value, interpreter: &mut Interpreter) -> Result<(), Value> {
+        let mut bindings = vec![];
+        if !self.destructure(value, &mut bindings, interpreter) {
+            return Err(new_condition!(format!("{}: can't destructure {} with pattern {}",
+                form, value.to_string(&interpreter.interner), self.to_string(&interpreter.interner))));
+        }
+
+        for (id, value) in bindings {
+            interpreter.current_scope.add_symbol(id, value);
+        }
+        Ok(())
+    }
+
+    // binds the args of a procedure or recur, for a flat list of names only their number can be wrong,
+    // which is reported as arity mismatch like for natives
+    pub fn bind_args(&self, name: &str, args: &[Value], interpreter: &mut Interpreter) -> Result<(), Value> {
+        if let &Pattern::List(ref patterns, ref rest) = self {
+            let flat = patterns.iter().chain(rest.iter().map(|x| &**x)).all(|p| match p { &Pattern::Symbol(_) => true, _ => false });
+            let expected = match rest {
+                &None if flat && args.len() != patterns.len() => Some(format!("{}", patterns.len())),
+                &Some(_) if flat && args.len() < patterns.len() => Some(format!("{}..", patterns.len())),
+                _ => None,
+            };
+            if let Some(expected) = expected {
+                return Err(new_condition!(format!("arity mismatch for {}: expected: {}, got: {}", name, expected, args.len())));
+            }
+        }
+        self.bind(name, &Value::new_list(args), interpreter)
+    }
+
+    // the pattern as it's written, see to_string
+    pub fn to_data(&self, interner: &mut StringInterner) -> Value {
+        match self {
+            &Pattern::Wildcard => Value::new_symbol(interner.intern("_")),
+            &Pattern::Symbol(id) => Value::new_symbol(id),
+            &Pattern::Literal(ref literal) => {
+                if literal.get_symbol().is_some() || literal.get_list().is_some() {
+                    Value::new_list(&[Value::new_symbol(interner.intern("quote")), literal.clone()])
+                } else {
+                    literal.clone()
+                }
+            },
+            &Pattern::Predicate(predicate, ref pattern) => {
+                Value::new_list(&[Value::new_symbol(interner.intern("?")), Value::new_symbol(predicate), pattern.to_data(interner)])
+            },
+            &Pattern::List(ref patterns, ref rest) => {
+                let mut elements: Vec<Value> = patterns.iter().map(|p| p.to_data(interner)).collect();
+                if let &Some(ref rest) = rest {
+                    elements.push(Value::new_symbol(interner.intern("&")));
+                    elements.push(rest.to_data(interner));
+                }
+                Value::new_list(&elements)
+            },
+        }
+    }
+
+    pub fn to_string(&self, interner: &StringInterner) -> String {
+        match self {
+            &Pattern::Wildcard => "_".into(),
+            &Pattern::Symbol(id) => interner.lookup(id).map(Into::into).unwrap_or(format!("[SYMBOL: {}]", id)),
+            &Pattern::Literal(ref literal) => {
+                // symbols and lists have to be quoted, everything else stands for itself
+                if literal.get_symbol().is_some() || literal.get_list().is_some() {
+                    format!("'{}", literal.to_string(interner))
+                } else {
+                    literal.to_string(interner)
+                }
+            },
+            &Pattern::Predicate(predicate, ref pattern) => {
+                format!("(? {} {})", Pattern::Symbol(predicate).to_string(interner), pattern.to_string(interner))
+            },
+            &Pattern::List(ref patterns, ref rest) => {
+                let patterns = patterns.iter().map(|p| p.to_string(interner));
+                let rest = rest.iter().map(|p| format!("& {}", p.to_string(interner)));
+                format!("({})", patterns.chain(rest).join(" "))
+            },
+        }
+    }
+}
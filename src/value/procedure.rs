@@ -1,4 +1,4 @@
-use ::value::{Value, LetLoop};
+use ::value::{Value, Pattern, recursion_point};
 use ::scope::Scope;
 use ::interpreter::Interpreter;
 use ::string_interner::StringInterner;
@@ -8,12 +8,12 @@ use itertools::Itertools;
 pub struct Proc {
     name: Option<String>,
     parent_scope: Scope,
-    bindings: Vec<u64>,
+    bindings: Pattern,
     code: Vec<Value>,
 }
 
 impl Proc {
-    pub fn new(name: Option<String>, parent_scope: Scope, bindings: Vec<u64>, code: Vec<Value>) -> Self {
+    pub fn new(name: Option<String>, parent_scope: Scope, bindings: Pattern, code: Vec<Value>) -> Self {
         Proc {
             name: name,
             parent_scope: parent_scope,
@@ -23,24 +23,44 @@ impl Proc {
     }
 
     pub fn evaluate(&self, interpreter: &mut Interpreter, args: &[Value]) -> Value {
-        // every function's body is enclosed in an implicit loop
-        let bindings: Vec<(u64, Value)> = self.bindings.iter().cloned().zip(args.iter().cloned()).collect();
-        let implicit_loop = LetLoop::new(bindings, self.code.clone());
-        implicit_loop.evaluate_loop(interpreter)
+        // args are evaluated in the scope of the caller
+        let args: Vec<Value> = args.iter().map(|arg| interpreter.evaluate(arg)).collect();
+        self.apply(interpreter, &args)
     }
 
+    // calls the procedure with already evaluated args
+    pub fn apply(&self, interpreter: &mut Interpreter, args: &[Value]) -> Value {
+        // the body is evaluated in a child of the scope the procedure was created in, not of the caller's scope,
+        // so free names refer to the definitions around the lambda and returned lambdas keep seeing their args
+        let caller_scope = interpreter.current_scope.clone();
+        interpreter.current_scope = self.parent_scope.new_child();
 
-    pub fn to_string(&self, interner: &StringInterner) -> String {
         let name = self.name.as_ref().map(|x| &**x).unwrap_or("lambda");
+        let res = match self.bindings.bind_args(name, args, interpreter) {
+            // every function's body is enclosed in an implicit loop
+            Ok(()) => recursion_point(interpreter, name, &self.parent_scope, &self.bindings, &self.code),
+            Err(condition) => condition,
+        };
 
-        let bindings = self.bindings.iter()
-        .flat_map(|&b| interner.lookup(b))
-        .join(" ");
+        interpreter.current_scope = caller_scope;
+        res
+    }
+
+    pub fn parent_scope(&self) -> &Scope {
+        &self.parent_scope
+    }
+
+    pub fn code(&self) -> &[Value] {
+        &self.code
+    }
+
+    pub fn to_string(&self, interner: &StringInterner) -> String {
+        let name = self.name.as_ref().map(|x| &**x).unwrap_or("lambda");
 
         let code = self.code.iter()
         .map(|x| x.to_string(interner))
         .join(" ");
 
-        format!("({} ({}) {})", name, bindings, code)
+        format!("({} {} {})", name, self.bindings.to_string(interner), code)
     }
 }
@@ -1,16 +1,24 @@
-use ::value::Value;
+use std::iter;
+use ::value::{Value, Pattern, SyntaxRules};
 use ::interpreter::Interpreter;
+use ::scope::Scope;
+use ::string_interner::StringInterner;
+use itertools::Itertools;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum SpecialForm {
     Begin(Begin),
     Define(Define),
+    Defmacro(Defmacro),
+    DefineSyntax(DefineSyntax),
     If(If),
     Lambda(Lambda),
     Let(LetLoop),
     Loop(LetLoop),
     RecurForm(RecurForm),
     Quote(Quote),
+    Quasiquote(Quasiquote),
+    Match(Match),
 }
 
 impl SpecialForm {
@@ -18,14 +26,107 @@ impl SpecialForm {
         match self {
             &SpecialForm::Begin(ref x) => x.evaluate(interpreter),
             &SpecialForm::Define(ref x) => x.evaluate(interpreter),
+            &SpecialForm::Defmacro(ref x) => x.evaluate(interpreter),
+            &SpecialForm::DefineSyntax(ref x) => x.evaluate(interpreter),
             &SpecialForm::If(ref x) => x.evaluate(interpreter),
             &SpecialForm::Lambda(ref x) => x.evaluate(interpreter),
             &SpecialForm::Let(ref x) => x.evaluate_let(interpreter),
             &SpecialForm::Loop(ref x) => x.evaluate_loop(interpreter),
             &SpecialForm::RecurForm(ref x) => x.evaluate(interpreter),
             &SpecialForm::Quote(ref x) => x.evaluate(interpreter),
+            &SpecialForm::Quasiquote(ref x) => x.evaluate(interpreter),
+            &SpecialForm::Match(ref x) => x.evaluate(interpreter),
         }
     }
+
+    // prints the form as source code, which parses to the same form again
+    pub fn to_string(&self, interner: &StringInterner) -> String {
+        match self {
+            &SpecialForm::Begin(ref x) => list_string("begin", &[], &x.code, interner),
+            &SpecialForm::Define(ref x) => {
+                let name = Value::new_symbol(x.symbol_id).to_string(interner);
+                format!("(define {} {})", name, x.expression.to_string(interner))
+            },
+            &SpecialForm::Defmacro(ref x) => {
+                let head = &[Value::new_symbol(x.symbol_id).to_string(interner), x.bindings.to_string(interner)];
+                list_string("defmacro", head, &x.code, interner)
+            },
+            &SpecialForm::DefineSyntax(ref x) => {
+                let name = Value::new_symbol(x.symbol_id).to_string(interner);
+                format!("(define-syntax {} {})", name, x.spec.to_string(interner))
+            },
+            &SpecialForm::If(ref x) => list_string("if", &[], &[x.condition.clone(), x.then.clone(), x.or_else.clone()], interner),
+            &SpecialForm::Lambda(ref x) => {
+                let head: Vec<String> = x.name.iter().cloned().chain(iter::once(x.bindings.to_string(interner))).collect();
+                list_string("lambda", &head, &x.code, interner)
+            },
+            &SpecialForm::Let(ref x) => list_string("let", &[x.bindings_string(interner)], &x.code, interner),
+            &SpecialForm::Loop(ref x) => list_string("loop", &[x.bindings_string(interner)], &x.code, interner),
+            &SpecialForm::RecurForm(ref x) => list_string("recur", &[], &x.bindings, interner),
+            &SpecialForm::Quote(ref x) => format!("(quote {})", x.expression.to_string(interner)),
+            &SpecialForm::Quasiquote(ref x) => format!("`{}", x.template.to_string(interner)),
+            &SpecialForm::Match(ref x) => {
+                let clauses = x.clauses.iter().map(|c| c.to_string(interner)).join(" ");
+                format!("(match {} {})", x.expression.to_string(interner), clauses)
+            },
+        }
+    }
+
+    // the form as data, which compiles to the same form again
+    pub fn to_data(&self, interner: &mut StringInterner) -> Value {
+        match self {
+            &SpecialForm::Begin(ref x) => list_data("begin", vec![], &x.code, interner),
+            &SpecialForm::Define(ref x) => {
+                let expression = x.expression.to_data(interner);
+                list_data("define", vec![Value::new_symbol(x.symbol_id), expression], &[], interner)
+            },
+            &SpecialForm::Defmacro(ref x) => {
+                let head = vec![Value::new_symbol(x.symbol_id), x.bindings.to_data(interner)];
+                list_data("defmacro", head, &x.code, interner)
+            },
+            &SpecialForm::DefineSyntax(ref x) => list_data("define-syntax", vec![Value::new_symbol(x.symbol_id), x.spec.clone()], &[], interner),
+            &SpecialForm::If(ref x) => list_data("if", vec![], &[x.condition.clone(), x.then.clone(), x.or_else.clone()], interner),
+            &SpecialForm::Lambda(ref x) => {
+                let mut head: Vec<Value> = x.name.iter().map(|name| Value::new_symbol(interner.intern(name))).collect();
+                head.push(x.bindings.to_data(interner));
+                list_data("lambda", head, &x.code, interner)
+            },
+            &SpecialForm::Let(ref x) => {
+                let bindings = x.bindings_data(interner);
+                list_data("let", vec![bindings], &x.code, interner)
+            },
+            &SpecialForm::Loop(ref x) => {
+                let bindings = x.bindings_data(interner);
+                list_data("loop", vec![bindings], &x.code, interner)
+            },
+            &SpecialForm::RecurForm(ref x) => list_data("recur", vec![], &x.bindings, interner),
+            &SpecialForm::Quote(ref x) => list_data("quote", vec![x.expression.clone()], &[], interner),
+            &SpecialForm::Quasiquote(ref x) => {
+                let template = x.template.to_data(interner);
+                list_data("quasiquote", vec![template], &[], interner)
+            },
+            &SpecialForm::Match(ref x) => {
+                let expression = x.expression.to_data(interner);
+                let clauses: Vec<Value> = x.clauses.iter().map(|c| c.to_data(interner)).collect();
+                list_data("match", iter::once(expression).chain(clauses).collect(), &[], interner)
+            },
+        }
+    }
+}
+
+// (name head* code*), the head is data already
+fn list_data(name: &str, head: Vec<Value>, code: &[Value], interner: &mut StringInterner) -> Value {
+    let mut elements = vec![Value::new_symbol(interner.intern(name))];
+    elements.extend(head);
+    elements.extend(code.iter().map(|x| x.to_data(interner)));
+    Value::new_list(&elements)
+}
+
+// (name head* code*)
+fn list_string(name: &str, head: &[String], code: &[Value], interner: &StringInterner) -> String {
+    let code = code.iter().map(|x| x.to_string(interner));
+    let mut elements = iter::once(name.to_string()).chain(head.iter().cloned()).chain(code);
+    format!("({})", elements.join(" "))
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -75,6 +176,54 @@ impl Define {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct Defmacro {
+    symbol_id: u64,
+    bindings: Pattern,
+    code: Vec<Value>,
+}
+
+impl Defmacro {
+    pub fn new(symbol_id: u64, bindings: Pattern, code: Vec<Value>) -> Self {
+        Defmacro {
+            symbol_id: symbol_id,
+            bindings: bindings,
+            code: code,
+        }
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Value {
+        let name = Value::new_symbol(self.symbol_id).to_string(&interpreter.interner);
+        let mac = Value::new_macro(name, interpreter.current_scope.clone(), self.bindings.clone(), self.code.clone());
+        interpreter.current_scope.add_symbol(self.symbol_id, mac);
+        Value::new_symbol(self.symbol_id)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DefineSyntax {
+    symbol_id: u64,
+    // the syntax-rules form as data
+    spec: Value,
+    rules: SyntaxRules,
+}
+
+impl DefineSyntax {
+    pub fn new(symbol_id: u64, spec: Value, rules: SyntaxRules) -> Self {
+        DefineSyntax {
+            symbol_id: symbol_id,
+            spec: spec,
+            rules: rules,
+        }
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Value {
+        let rules = Value::new_syntax_rules(self.rules.clone(), interpreter.current_scope.clone());
+        interpreter.current_scope.add_symbol(self.symbol_id, rules);
+        Value::new_symbol(self.symbol_id)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Quote {
     expression: Value,
@@ -92,15 +241,90 @@ impl Quote {
     }
 }
 
+// data with holes, the holes are filled with the values of their code when the quasiquote is evaluated
+#[derive(Debug, PartialEq, Clone)]
+pub enum QuasiTemplate {
+    Datum(Value),
+    Unquote(Value),
+    // only valid as element of a list, the elements of the resulting list are spliced in
+    UnquoteSplicing(Value),
+    List(Vec<QuasiTemplate>),
+}
+
+impl QuasiTemplate {
+    fn to_string(&self, interner: &StringInterner) -> String {
+        match self {
+            &QuasiTemplate::Datum(ref x) => x.to_string(interner),
+            &QuasiTemplate::Unquote(ref code) => format!(",{}", code.to_string(interner)),
+            &QuasiTemplate::UnquoteSplicing(ref code) => format!(",@{}", code.to_string(interner)),
+            &QuasiTemplate::List(ref templates) => format!("({})", templates.iter().map(|t| t.to_string(interner)).join(" ")),
+        }
+    }
+
+    fn to_data(&self, interner: &mut StringInterner) -> Value {
+        match self {
+            &QuasiTemplate::Datum(ref x) => x.clone(),
+            &QuasiTemplate::Unquote(ref code) => list_data("unquote", vec![], &[code.clone()], interner),
+            &QuasiTemplate::UnquoteSplicing(ref code) => list_data("unquote-splicing", vec![], &[code.clone()], interner),
+            &QuasiTemplate::List(ref templates) => Value::new_list(&templates.iter().map(|t| t.to_data(interner)).collect::<Vec<_>>()),
+        }
+    }
+
+    fn fill(&self, interpreter: &mut Interpreter) -> Result<Value, Value> {
+        match self {
+            &QuasiTemplate::Datum(ref x) => Ok(x.clone()),
+            &QuasiTemplate::Unquote(ref code) => Ok(interpreter.evaluate(code)),
+            &QuasiTemplate::UnquoteSplicing(_) => Err(new_condition!("unquote-splicing is only valid inside of a list")),
+            &QuasiTemplate::List(ref templates) => {
+                let mut values = vec![];
+                for template in templates {
+                    match template {
+                        &QuasiTemplate::UnquoteSplicing(ref code) => {
+                            let list = interpreter.evaluate(code);
+                            match list.get_list() {
+                                Some(elements) => values.extend(elements),
+                                None => return Err(new_condition!(format!("unquote-splicing expected list, found: {}",
+                                    list.to_string(&interpreter.interner)))),
+                            }
+                        },
+                        x => values.push(x.fill(interpreter)?),
+                    }
+                }
+                Ok(Value::new_list(&values))
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quasiquote {
+    template: QuasiTemplate,
+}
+
+impl Quasiquote {
+    pub fn new(template: QuasiTemplate) -> Self {
+        Quasiquote {
+            template: template,
+        }
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Value {
+        match self.template.fill(interpreter) {
+            Ok(x) => x,
+            Err(condition) => condition,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Lambda {
     name: Option<String>,
-    bindings: Vec<u64>,
+    bindings: Pattern,
     code: Vec<Value>,
 }
 
 impl Lambda {
-    pub fn new(name: Option<String>, bindings: Vec<u64>, code: Vec<Value>) -> Self {
+    pub fn new(name: Option<String>, bindings: Pattern, code: Vec<Value>) -> Self {
         Lambda {
             name: name,
             bindings: bindings,
@@ -115,28 +339,54 @@ impl Lambda {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct LetLoop {
-    bindings: Vec<(u64, Value)>,
+    bindings: Vec<(Pattern, Value)>,
     code: Vec<Value>,
 }
 
 impl LetLoop {
-    pub fn new(bindings: Vec<(u64, Value)>, code: Vec<Value>) -> Self {
+    pub fn new(bindings: Vec<(Pattern, Value)>, code: Vec<Value>) -> Self {
         LetLoop {
             bindings: bindings,
             code: code,
         }
     }
 
-    fn evaluate_let(&self, interpreter: &mut Interpreter) -> Value {
-        // replace interpreter scope with fresh child scope
+    // evaluates bindings sequentially in a fresh child scope, which replaces the interpreter scope
+    // returns the old scope, which has to be restored afterwards
+    fn bind(&self, name: &str, interpreter: &mut Interpreter) -> Result<Scope, Value> {
         let parent_scope = interpreter.current_scope.clone();
         interpreter.current_scope = parent_scope.new_child();
 
-        // evaluate bindings sequentially in fresh scope
-        for &(binding_name, ref binding_value) in &self.bindings {
+        for &(ref pattern, ref binding_value) in &self.bindings {
             let binding_value = interpreter.evaluate(binding_value);
-            interpreter.current_scope.add_symbol(binding_name, binding_value);
+            if let Err(condition) = pattern.bind(name, &binding_value, interpreter) {
+                interpreter.current_scope = parent_scope;
+                return Err(condition);
+            }
+        }
+        Ok(parent_scope)
+    }
+
+    fn bindings_data(&self, interner: &mut StringInterner) -> Value {
+        let mut bindings = vec![];
+        for &(ref pattern, ref value) in &self.bindings {
+            bindings.push(pattern.to_data(interner));
+            bindings.push(value.to_data(interner));
         }
+        Value::new_list(&bindings)
+    }
+
+    fn bindings_string(&self, interner: &StringInterner) -> String {
+        let mut bindings = self.bindings.iter()
+        .map(|&(ref pattern, ref value)| format!("{} {}", pattern.to_string(interner), value.to_string(interner)));
+        format!("({})", bindings.join(" "))
+    }
+
+    fn evaluate_let(&self, interpreter: &mut Interpreter) -> Value {
+        let parent_scope = match self.bind("let", interpreter) {
+            Ok(scope) => scope,
+            Err(condition) => return condition,
+        };
 
         // evaluate body with new scope and bindings
         let mut res = Value::empty_list();
@@ -150,44 +400,49 @@ impl LetLoop {
         res
     }
 
-    // pub because this is also used for procs
-    pub fn evaluate_loop(&self, interpreter: &mut Interpreter) -> Value {
-        // replace interpreter scope with fresh child scope
-        let parent_scope = interpreter.current_scope.clone();
-        interpreter.current_scope = parent_scope.new_child();
+    fn evaluate_loop(&self, interpreter: &mut Interpreter) -> Value {
+        let parent_scope = match self.bind("loop", interpreter) {
+            Ok(scope) => scope,
+            Err(condition) => return condition,
+        };
 
-        // evaluate bindings sequentially in fresh scope
-        for &(binding_name, ref binding_value) in &self.bindings {
-            let binding_value = interpreter.evaluate(binding_value);
-            interpreter.current_scope.add_symbol(binding_name, binding_value);
-        }
+        // recur supplies one value per binding
+        let patterns = self.bindings.iter().map(|&(ref pattern, _)| pattern.clone()).collect();
+        let res = recursion_point(interpreter, "loop", &parent_scope, &Pattern::List(patterns, None), &self.code);
 
-        let mut res = Value::empty_list();
-        loop {
-            // evaluate body with new scope and bindings
-            for body in &self.code {
-                res = interpreter.evaluate(body);
-            }
-
-            // check for recursion
-            if let Some(args) = res.get_recur() {
-                check_arity!("loop", args.len(), self.bindings.len() as u32);
+        // restore old scope
+        interpreter.current_scope = parent_scope;
+        res
+    }
+}
 
-                // recreate a new scope
-                interpreter.current_scope = parent_scope.new_child();
+// Evaluates code repeatedly in the current scope until it doesn't return a recur value.
+// The args of recur are destructured with pattern in a fresh child of parent_scope.
+// This is used by loop and procs.
+pub fn recursion_point(interpreter: &mut Interpreter, name: &str, parent_scope: &Scope, pattern: &Pattern, code: &[Value]) -> Value {
+    let mut res = Value::empty_list();
+    loop {
+        // evaluate body with current scope and bindings
+        interpreter.recursion_points += 1;
+        for body in code {
+            res = interpreter.evaluate(body);
+        }
+        interpreter.recursion_points -= 1;
 
-                // bind values from recur
-                for (binding_name, binding_value) in self.bindings.iter().map(|&(name, _)| name).zip(args.iter()) {
-                    interpreter.current_scope.add_symbol(binding_name, binding_value.clone());
-                }
+        // check for recursion
+        if let Some(args) = res.get_recur() {
+            // recreate a new scope
+            interpreter.current_scope = parent_scope.new_child();
 
-                continue;
+            // bind values from recur
+            if let Err(condition) = pattern.bind_args(name, args, interpreter) {
+                return condition;
             }
 
-            // restore old scope
-            interpreter.current_scope = parent_scope;
-            return res;
+            continue;
         }
+
+        return res;
     }
 }
 
@@ -204,6 +459,8 @@ impl RecurForm {
     }
 
     fn evaluate(&self, interpreter: &mut Interpreter) -> Value {
+        // the grammar only allows recur in tail position, but make sure it never escapes a recursion point
+        assert_or_condition!(interpreter.recursion_points > 0, "recur outside of a recursion point, recur can only be used in tail position of a lambda or loop");
         let evaluated_bindings = self.bindings.iter().map(|b| interpreter.evaluate(b)).collect();
         Value::new_recur(evaluated_bindings)
     }
@@ -226,3 +483,107 @@ impl Begin {
         evaluated_code.unwrap_or(Value::empty_list())
     }
 }
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    expression: Value,
+    clauses: Vec<MatchClause>,
+}
+
+impl Match {
+    pub fn new(expression: Value, clauses: Vec<MatchClause>) -> Self {
+        Match {
+            expression: expression,
+            clauses: clauses,
+        }
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Value {
+        let value = interpreter.evaluate(&self.expression);
+
+        for clause in &self.clauses {
+            let mut bindings = vec![];
+            if !clause.pattern.destructure(&value, &mut bindings, interpreter) {
+                continue;
+            }
+
+            // replace interpreter scope with fresh child scope containing the bindings of the pattern
+            let parent_scope = interpreter.current_scope.clone();
+            interpreter.current_scope = parent_scope.new_child();
+            for (binding_name, binding_value) in bindings {
+                interpreter.current_scope.add_symbol(binding_name, binding_value);
+            }
+
+            if let Some(ref guard) = clause.guard {
+                let guard = interpreter.evaluate(guard);
+                match guard.get_bool() {
+                    Some(true) => (),
+                    Some(false) => {
+                        interpreter.current_scope = parent_scope;
+                        continue;
+                    },
+                    None => {
+                        interpreter.current_scope = parent_scope;
+                        raise_condition!(format!("match guard expected bool, found: {}", guard.to_string(&interpreter.interner)))
+                    },
+                }
+            }
+
+            // evaluate body with new scope and bindings
+            let mut res = Value::empty_list();
+            for body in &clause.code {
+                res = interpreter.evaluate(body);
+            }
+
+            // restore old scope
+            interpreter.current_scope = parent_scope;
+            return res;
+        }
+
+        raise_condition!(format!("match: no clause matches {}", value.to_string(&interpreter.interner)))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchClause {
+    pattern: Pattern,
+    guard: Option<Value>,
+    code: Vec<Value>,
+}
+
+impl MatchClause {
+    // code is the rest of the clause after the pattern, if its first element is the symbol guard_marker
+    // the second one is the guard and the remaining ones are the body
+    // returns None if there's a guard_marker, but no guard or body
+    pub fn with_guard(pattern: Pattern, mut code: Vec<Value>, guard_marker: u64) -> Option<Self> {
+        let mut guard = None;
+        if code[0].get_symbol() == Some(guard_marker) {
+            if code.len() < 3 { return None; }
+            guard = Some(code[1].clone());
+            code.drain(..2);
+        }
+
+        Some(MatchClause {
+            pattern: pattern,
+            guard: guard,
+            code: code,
+        })
+    }
+
+    fn to_data(&self, interner: &mut StringInterner) -> Value {
+        let mut elements = vec![self.pattern.to_data(interner)];
+        if let Some(ref guard) = self.guard {
+            elements.push(Value::new_symbol(interner.intern(":when")));
+            elements.push(guard.to_data(interner));
+        }
+        elements.extend(self.code.iter().map(|x| x.to_data(interner)));
+        Value::new_list(&elements)
+    }
+
+    fn to_string(&self, interner: &StringInterner) -> String {
+        let guard = self.guard.iter().map(|g| format!(":when {}", g.to_string(interner)));
+        let code = self.code.iter().map(|x| x.to_string(interner));
+        let mut elements = iter::once(self.pattern.to_string(interner)).chain(guard).chain(code);
+        format!("({})", elements.join(" "))
+    }
+}
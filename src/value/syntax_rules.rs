@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use ::value::Value;
+use ::string_interner::StringInterner;
+
+// Macros in the style of R7RS syntax-rules:
+// (syntax-rules (literal*) (pattern template)+)
+// A form is rewritten with the template of the first rule whose pattern matches it.
+// The macros are hygienic: symbols introduced by a template are renamed to fresh symbols, so bindings
+// of the expansion can't capture the code passed to the macro. The renamed symbols, which the expansion
+// doesn't bind, are resolved where the macro was defined, see Interpreter::lookup_symbol.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxRules {
+    literals: Vec<u64>,
+    rules: Vec<(Value, Value)>,
+    ellipsis: u64,
+    wildcard: u64,
+}
+
+// what a pattern variable is bound to, variables followed by an ellipsis are bound to a sequence
+#[derive(Debug, Clone)]
+enum Binding {
+    One(Value),
+    Many(Vec<Binding>),
+}
+
+type Bindings = HashMap<u64, Binding>;
+
+impl SyntaxRules {
+    // creates syntax rules from their specification as data, returns None if it is malformed
+    pub fn new(spec: &Value, interner: &mut StringInterner) -> Option<Self> {
+        let spec = spec.get_list().unwrap_or(vec![]);
+        if spec.len() < 3 || spec[0].get_symbol() != Some(interner.intern("syntax-rules")) {
+            return None;
+        }
+
+        let literals = match spec[1].get_list().and_then(|l| l.iter().map(Value::get_symbol).collect()) {
+            Some(literals) => literals,
+            None => return None,
+        };
+
+        let mut syntax_rules = SyntaxRules {
+            literals: literals,
+            rules: vec![],
+            ellipsis: interner.intern("..."),
+            wildcard: interner.intern("_"),
+        };
+
+        for rule in &spec[2..] {
+            let rule = rule.get_list().unwrap_or(vec![]);
+            if rule.len() != 2 || rule[0].get_list().map_or(true, |p| p.is_empty()) {
+                return None;
+            }
+            // the first element of the pattern stands for the macro name, so it can't be repeated
+            if !syntax_rules.valid_pattern(&Value::new_list(&rule[0].get_list().unwrap()[1..])) {
+                return None;
+            }
+            syntax_rules.rules.push((rule[0].clone(), rule[1].clone()));
+        }
+        Some(syntax_rules)
+    }
+
+    // at most one ellipsis per list, which has to follow a pattern
+    fn valid_pattern(&self, pattern: &Value) -> bool {
+        match pattern.get_list() {
+            Some(list) => {
+                let ellipses: Vec<usize> = list.iter().enumerate()
+                .filter(|&(_, x)| x.get_symbol() == Some(self.ellipsis))
+                .map(|(i, _)| i)
+                .collect();
+                ellipses.len() <= 1 && ellipses.iter().all(|&i| i > 0) && list.iter().all(|x| self.valid_pattern(x))
+            },
+            None => true,
+        }
+    }
+
+    // rewrites form, whose first element is the name of the macro
+    // also returns the fresh symbols of the expansion with their original symbols
+    pub fn expand(&self, form: &Value, interner: &mut StringInterner) -> Result<(Value, HashMap<u64, u64>), Value> {
+        let form_list = form.get_list().unwrap_or(vec![]);
+
+        for &(ref pattern, ref template) in &self.rules {
+            // the first element of the pattern stands for the macro name and is ignored
+            let pattern = Value::new_list(&pattern.get_list().unwrap()[1..]);
+            let mut bindings = Bindings::new();
+            if !self.match_pattern(&pattern, &Value::new_list(&form_list[1..]), &mut bindings) {
+                continue;
+            }
+
+            let mut fresh = HashMap::new();
+            let expanded = self.instantiate(template, &bindings, &mut fresh, interner)
+            .map_err(|msg| new_condition!(format!("{}: {}", form_list[0].to_string(interner), msg)))?;
+            let renames = fresh.into_iter().map(|(original, fresh)| (fresh, original)).collect();
+            let expanded = restore_names(&expanded, &renames, 0, interner);
+            return Ok((expanded, renames));
+        }
+
+        Err(new_condition!(format!("{}: no syntax rule matches {}", form_list[0].to_string(interner), form.to_string(interner))))
+    }
+
+    fn match_pattern(&self, pattern: &Value, form: &Value, bindings: &mut Bindings) -> bool {
+        if let Some(id) = pattern.get_symbol() {
+            if id == self.wildcard {
+                return true;
+            }
+            if self.literals.contains(&id) {
+                return form.get_symbol() == Some(id);
+            }
+            bindings.insert(id, Binding::One(form.clone()));
+            return true;
+        }
+
+        let patterns = match pattern.get_list() {
+            Some(patterns) => patterns,
+            None => return pattern == form,
+        };
+        let forms = match form.get_list() {
+            Some(forms) => forms,
+            None => return false,
+        };
+
+        match patterns.iter().position(|p| p.get_symbol() == Some(self.ellipsis)) {
+            None => {
+                patterns.len() == forms.len() &&
+                patterns.iter().zip(forms.iter()).all(|(p, f)| self.match_pattern(p, f, bindings))
+            },
+            Some(ellipsis) => {
+                // the pattern before the ellipsis matches as many forms as aren't needed by the patterns after it
+                let before = &patterns[..ellipsis - 1];
+                let after = &patterns[ellipsis + 1..];
+                if forms.len() < before.len() + after.len() {
+                    return false;
+                }
+                let repeated_end = forms.len() - after.len();
+
+                let fixed_match =
+                    before.iter().zip(forms.iter()).all(|(p, f)| self.match_pattern(p, f, bindings)) &&
+                    after.iter().zip(forms[repeated_end..].iter()).all(|(p, f)| self.match_pattern(p, f, bindings));
+                if !fixed_match {
+                    return false;
+                }
+
+                let repeated = &patterns[ellipsis - 1];
+                let mut matches = vec![];
+                for form in &forms[before.len()..repeated_end] {
+                    let mut repeated_bindings = Bindings::new();
+                    if !self.match_pattern(repeated, form, &mut repeated_bindings) {
+                        return false;
+                    }
+                    matches.push(repeated_bindings);
+                }
+
+                for var in self.variables(repeated) {
+                    let sequence = matches.iter_mut().map(|m| m.remove(&var).unwrap()).collect();
+                    bindings.insert(var, Binding::Many(sequence));
+                }
+                true
+            },
+        }
+    }
+
+    // pattern variables in pattern
+    fn variables(&self, pattern: &Value) -> Vec<u64> {
+        match pattern.get_symbol() {
+            Some(id) if id == self.wildcard || id == self.ellipsis || self.literals.contains(&id) => vec![],
+            Some(id) => vec![id],
+            None => pattern.get_list().unwrap_or(vec![]).iter().flat_map(|p| self.variables(p)).collect(),
+        }
+    }
+
+    // fills in the bindings, symbols introduced by the template are replaced by fresh symbols,
+    // fresh maps the original symbols to the fresh ones
+    fn instantiate(&self, template: &Value, bindings: &Bindings, fresh: &mut HashMap<u64, u64>, interner: &mut StringInterner)
+    -> Result<Value, String> {
+        if let Some(id) = template.get_symbol() {
+            return match bindings.get(&id) {
+                Some(&Binding::One(ref value)) => Ok(value.clone()),
+                Some(&Binding::Many(_)) => Err(format!("{} has to be followed by ...", template.to_string(interner))),
+                None => {
+                    let fresh_id = *fresh.entry(id).or_insert_with(|| interner.gensym(id));
+                    Ok(Value::new_symbol(fresh_id))
+                },
+            };
+        }
+
+        let templates = match template.get_list() {
+            Some(templates) => templates,
+            None => return Ok(template.clone()),
+        };
+
+        let mut expanded = vec![];
+        let mut i = 0;
+        while i < templates.len() {
+            let followed_by_ellipsis = templates.get(i + 1).and_then(Value::get_symbol) == Some(self.ellipsis);
+            if !followed_by_ellipsis {
+                expanded.push(self.instantiate(&templates[i], bindings, fresh, interner)?);
+                i += 1;
+                continue;
+            }
+
+            // repeat the template once for each element of the sequences bound to its variables
+            let sequences: Vec<(u64, &Vec<Binding>)> = self.variables(&templates[i]).into_iter()
+            .filter_map(|var| match bindings.get(&var) {
+                Some(&Binding::Many(ref sequence)) => Some((var, sequence)),
+                _ => None,
+            })
+            .collect();
+
+            let len = match sequences.first() {
+                Some(&(_, sequence)) => sequence.len(),
+                None => return Err(format!("no pattern variable to repeat in {}", templates[i].to_string(interner))),
+            };
+            if sequences.iter().any(|&(_, sequence)| sequence.len() != len) {
+                return Err(format!("pattern variables in {} repeat a different number of times", templates[i].to_string(interner)));
+            }
+
+            for n in 0..len {
+                let mut repeated_bindings = bindings.clone();
+                for &(var, sequence) in &sequences {
+                    repeated_bindings.insert(var, sequence[n].clone());
+                }
+                expanded.push(self.instantiate(&templates[i], &repeated_bindings, fresh, interner)?);
+            }
+            i += 2;
+        }
+        Ok(Value::new_list(&expanded))
+    }
+}
+
+// symbols with a meaning of their own, which isn't looked up in a scope
+fn is_keyword(name: &str) -> bool {
+    const KEYWORDS: &'static [&'static str] = &[
+        "begin", "define", "defmacro", "define-syntax", "syntax-rules", "if", "let", "loop", "lambda", "recur", "quote",
+        "quasiquote", "unquote", "unquote-splicing", "match", "include", "module", "import", "export", "&", "_", "?", "...",
+    ];
+    KEYWORDS.contains(&name) || name.starts_with(':')
+}
+
+// in quoted data every symbol has the name the template author wrote
+const QUOTED: usize = ::std::usize::MAX;
+
+// Keywords and symbols in data get their original name back, the fresh symbols are kept everywhere else.
+// renames maps the fresh symbols to the original ones, depth is the number of quasiquotes around form,
+// not counting those left by unquotes, or QUOTED.
+fn restore_names(form: &Value, renames: &HashMap<u64, u64>, depth: usize, interner: &StringInterner) -> Value {
+    if let Some(id) = form.get_symbol() {
+        return match renames.get(&id) {
+            Some(&original) if depth > 0 || interner.lookup(original).map_or(false, is_keyword) => Value::new_symbol(original),
+            _ => form.clone(),
+        };
+    }
+
+    let list = match form.get_list() {
+        Some(ref list) if list.len() > 0 => list.clone(),
+        _ => return form.clone(),
+    };
+    let head = list[0].get_symbol().map(|id| *renames.get(&id).unwrap_or(&id)).and_then(|id| interner.lookup(id));
+    let depth = match head {
+        _ if depth == QUOTED => QUOTED,
+        Some("quote") if depth == 0 => QUOTED,
+        Some("quasiquote") => depth + 1,
+        Some("unquote") | Some("unquote-splicing") if depth > 0 => depth - 1,
+        _ => depth,
+    };
+    let list: Vec<Value> = list.iter().map(|x| restore_names(x, renames, depth, interner)).collect();
+    Value::new_list(&list)
+}
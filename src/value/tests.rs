@@ -1,5 +1,9 @@
-use ::value::Value;
+use ::value::{Value, HostObject};
 use ::string_interner::StringInterner;
+use ::interpreter::Interpreter;
+use ::grammar::parse;
+use ::compiler;
+use ::test_utils::{eval, eval_in};
 
 #[test]
 fn list_format() {
@@ -18,3 +22,329 @@ fn list_format() {
     assert_eq!(c.to_string(interner), "(2 3 4)");
     assert_eq!(d.to_string(interner), "(1 2 3 4)");
 }
+
+#[test]
+fn destructuring_let() {
+    assert_eq!(eval("(let ((a b) (list 1 2)) (list b a))"), "(2 1)");
+    assert_eq!(eval("(let ((a & more) (list 1 2 3)) more)"), "(2 3)");
+    assert_eq!(eval("(let ((a & more) (list 1)) more)"), "()");
+    assert_eq!(eval("(let ((a (b c) & d) (list 1 (list 2 3) 4 5)) (list a b c d))"), "(1 2 3 (4 5))");
+    assert_eq!(eval("(let (() (list)) 1)"), "1");
+}
+
+#[test]
+#[should_panic(expected = "let: can't destructure (1 2 3) with pattern (a b)")]
+fn destructuring_let_mismatch() {
+    eval("(let ((a b) (list 1 2 3)) a)");
+}
+
+#[test]
+fn destructuring_loop() {
+    assert_eq!(eval("(loop ((x & xs) (list 1 2 3) sum 0) (if (null? xs) (+ sum x) (recur xs (+ sum x))))"), "6");
+}
+
+#[test]
+fn destructuring_lambda() {
+    assert_eq!(eval("((lambda ((a b) c) (list a b c)) (list 1 2) 3)"), "(1 2 3)");
+    assert_eq!(eval("((lambda (& args) args) 1 2 3)"), "(1 2 3)");
+    assert_eq!(eval("((lambda (n & acc) (if (= n 0) acc (recur (- n 1) n))) 3)"), "(1)");
+    assert_eq!(eval("(define f (lambda (x) (lambda (y) (+ x y)))) ((f 1) 2)"), "3");
+}
+
+#[test]
+fn lexical_scope() {
+    let mut interpreter = Interpreter::new();
+    // the body sees the scope the lambda was created in, not the one of the caller
+    assert_eq!(eval_in(&mut interpreter, "(define x 1) (define f (lambda () x)) (let (x 2) (f))"), "1");
+    assert_eq!(eval_in(&mut interpreter, "(define g (lambda () y)) (let (y 1) (g))"), "error: undefined ident: y");
+    // but the args are evaluated in the scope of the caller
+    assert_eq!(eval_in(&mut interpreter, "(define id (lambda (z) z)) (let (z 3) (id z))"), "3");
+    assert_eq!(eval_in(&mut interpreter, "(define make (lambda (n) (lambda () n))) (list ((make 1)) ((make 2)))"), "(1 2)");
+}
+
+#[test]
+fn loop_scopes() {
+    let mut interpreter = Interpreter::new();
+    // every iteration binds in a fresh scope, so closures keep the values of their iteration
+    let closures = "(loop (i 0 fs '()) (if (= i 3) (map (lambda (f) (f)) fs) (recur (+ i 1) (cons (lambda () i) fs))))";
+    assert_eq!(eval_in(&mut interpreter, closures), "(2 1 0)");
+    assert_eq!(eval_in(&mut interpreter, "(loop (i 0) (define j i) (if (= i 2) j (recur (+ i 1))))"), "2");
+    assert_eq!(eval_in(&mut interpreter, "j"), "error: undefined ident: j");
+    // recur in a loop goes back to the loop, not to the lambda around it
+    assert_eq!(eval_in(&mut interpreter, "((lambda (n) (loop (i 0 acc 0) (if (= i n) acc (recur (+ i 1) (+ acc i))))) 4)"), "6");
+}
+
+#[test]
+fn lambda_arity_mismatch() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, "((lambda f (a b) a) 1)"), "error: arity mismatch for f: expected: 2, got: 1");
+    assert_eq!(eval_in(&mut interpreter, "((lambda f (a & b) a))"), "error: arity mismatch for f: expected: 1.., got: 0");
+    assert_eq!(eval_in(&mut interpreter, "(loop (a 1 b 2) (if (= a 1) (recur 2) a))"), "error: arity mismatch for loop: expected: 2, got: 1");
+    // only nested patterns can fail to destructure
+    assert_eq!(eval_in(&mut interpreter, "((lambda f ((a b)) a) (list 1))"), "error: f: can't destructure ((1)) with pattern ((a b))");
+}
+
+#[test]
+fn match_patterns() {
+    assert_eq!(eval("(match 2 (1 'one) (2 'two) (_ 'many))"), "two");
+    assert_eq!(eval(r#"(match "b" ("a" 1) ("b" 2))"#), "2");
+    assert_eq!(eval(r"(match #\b (#\a 1) (#\b 2))"), "2");
+    assert_eq!(eval("(match false (true 1) (false 2))"), "2");
+    assert_eq!(eval("(match (list 1 2) ((a b) (+ a b)))"), "3");
+    assert_eq!(eval("(match (list 1 2 3) ((a b) 'two) ((x & xs) xs))"), "(2 3)");
+    assert_eq!(eval("(match (list) ((x & xs) x) (() 'empty))"), "empty");
+    assert_eq!(eval("(match (list 'add 1 2) (('sub a b) (- a b)) (('add a b) (+ a b)))"), "3");
+    assert_eq!(eval("(match (list 1 (list 2 3)) ((1 (_ c)) c))"), "3");
+}
+
+#[test]
+fn match_predicates_and_guards() {
+    assert_eq!(eval(r#"(match "s" ((? integer? n) n) ((? string?) 'string))"#), "string");
+    assert_eq!(eval("(match 7 ((? integer? n) (+ n 1)) (_ 'no))"), "8");
+    assert_eq!(eval("(match 5 (n :when (> n 3) 'big) (n 'small))"), "big");
+    assert_eq!(eval("(match 2 (n :when (> n 3) 'big) (n 'small))"), "small");
+    assert_eq!(eval("(define small? (lambda (n) (< n 3))) (match 2 ((? small?) 'small) (_ 'big))"), "small");
+
+    // a failing predicate doesn't just skip the clause
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, "(match 1 ((? first) 'list) (_ 'other))"), "error: first expected list, got: 1");
+    assert_eq!(eval_in(&mut interpreter, "(match 1 ((? inc) 'one) (_ 'other))"), "error: match predicate inc expected bool, found: 2");
+}
+
+#[test]
+fn match_recur() {
+    let sum = "(loop (xs (list 1 2 3) acc 0) (match xs (() acc) ((x & rest) (recur rest (+ acc x)))))";
+    assert_eq!(eval(sum), "6");
+    let count = "(loop (n 0) (match n (3 'done) (_ :when (< n 3) (recur (+ n 1)))))";
+    assert_eq!(eval(count), "done");
+}
+
+#[test]
+#[should_panic(expected = "match: no clause matches 3")]
+fn match_without_matching_clause() {
+    eval("(match 3 (1 'one) (2 'two))");
+}
+
+#[test]
+fn quasiquote() {
+    assert_eq!(eval("`a"), "a");
+    assert_eq!(eval("`(1 2 3)"), "(1 2 3)");
+    assert_eq!(eval("(let (x 1 xs (list 2 3)) `(a ,x ,@xs b))"), "(a 1 2 3 b)");
+    assert_eq!(eval("`(1 ,@(list) 2)"), "(1 2)");
+    assert_eq!(eval("(let (x 1) `((a ,x) ,(+ x 1) 'x))"), "((a 1) 2 (quote x))");
+    assert_eq!(eval("(let (x 1) `,x)"), "1");
+    assert_eq!(eval("'(a `b ,c ,@d)"), "(a (quasiquote b) (unquote c) (unquote-splicing d))");
+
+    // only the holes of the outermost quasiquote are evaluated
+    assert_eq!(eval("(let (x 1) `(a `(b ,(c ,x))))"), "(a (quasiquote (b (unquote (c 1)))))");
+    assert_eq!(eval("(let (xs (list 1 2)) `(a `(,@xs ,,@xs)))"), "(a (quasiquote ((unquote-splicing xs) (unquote 1 2))))");
+
+    // which is what macros defining macros need
+    let constant = "(defmacro defconstant (name value) `(defmacro ,name () `(quote ,',value)))";
+    assert_eq!(eval(&format!("{} (defconstant answer (+ 40 2)) (answer)", constant)), "(+ 40 2)");
+}
+
+#[test]
+#[should_panic(expected = "unquote-splicing expected list, found: 1")]
+fn quasiquote_splicing_non_list() {
+    eval("`(a ,@1)");
+}
+
+#[test]
+fn defmacro() {
+    let when = "(defmacro when (condition & body) `(if ,condition (begin ,@body) (quote ())))";
+    assert_eq!(eval(&format!("{} (when true 1 2)", when)), "2");
+    assert_eq!(eval(&format!("{} (when false 1 2)", when)), "()");
+    assert_eq!(eval(&format!("{} (define f (lambda (x) (when (> x 1) 'big))) (f 2)", when)), "big");
+    assert_eq!(eval(&format!("{} (when true (when true 3))", when)), "3");
+
+    // macros can expand to other macro calls
+    let or = "(defmacro or (& xs) (if (null? xs) false `(let (x ,(first xs)) (if x x (or ,@(rest xs))))))";
+    assert_eq!(eval(&format!("{} (or false (= 1 1) undefined)", or)), "true");
+
+    // quoted data and binding positions aren't expanded
+    assert_eq!(eval(&format!("{} '(when true 1)", when)), "(when true 1)");
+    assert_eq!(eval(&format!("{} ((lambda (when x) (list when x)) 1 2)", when)), "(1 2)");
+    assert_eq!(eval(&format!("{} (let ((when x) (list 1 2)) x)", when)), "2");
+
+    // strings survive expansion
+    assert_eq!(eval(r#"(defmacro id (x) x) (id "a \"b\"\n")"#), r#""a \"b\"\n""#);
+
+    // the forms of a top-level begin are expanded one at a time, so later ones can use the macros of earlier ones
+    assert_eq!(eval("(begin (defmacro m2 (x) `(list ,x ,x)) (m2 1))"), "(1 1)");
+    let block = "(defmacro block (& body) `(begin ,@body))";
+    assert_eq!(eval(&format!("{} (block (defmacro m3 () 3) (m3))", block)), "3");
+}
+
+#[test]
+fn macroexpand() {
+    let unless = "(defmacro unless (c x) `(if ,c (quote ()) ,x)) (defmacro never (x) `(unless true ,x))";
+    assert_eq!(eval(&format!("{} (macroexpand-1 '(never 1))", unless)), "(unless true 1)");
+    assert_eq!(eval(&format!("{} (macroexpand '(never 1))", unless)), "(if true (quote ()) 1)");
+    assert_eq!(eval(&format!("{} (macroexpand '(list 1))", unless)), "(list 1)");
+}
+
+#[test]
+fn code_as_data() {
+    let interner = &mut StringInterner::new();
+    let source = "(begin (define f (lambda f ((a & b)) (if a (recur b) (list 'x `(1 ,a ,@b))))) \
+        (defmacro m (x) x) (define-syntax s (syntax-rules () ((_ a) a))) \
+        (loop (i 0) (if (= i 3) i (recur (+ i 1)))) (loop (i 0) i) (let (x 1) x) \
+        (match (list 1) ((? list? (a & _)) :when true a) ('x 2) (1 3) (_ 4)))";
+    let code = parse(source, interner).unwrap().pop().unwrap();
+    let data = code.to_data(interner);
+    assert_eq!(data.to_string(interner), source.split_whitespace().collect::<Vec<_>>().join(" ")
+        .replace("'x", "(quote x)").replace("`(1 ,a ,@b)", "(quasiquote (1 (unquote a) (unquote-splicing b)))"));
+    assert_eq!(compiler::compile(&data, interner), Ok(code));
+}
+
+#[test]
+fn macros_producing_recur() {
+    let again = "(defmacro again (& args) `(recur ,@args))";
+    assert_eq!(eval(&format!("{} (loop (i 0) (if (= i 3) i (again (+ i 1))))", again)), "3");
+    assert_eq!(eval(&format!("{} (eval '(loop (i 0) (if (= i 3) i (again (+ i 1)))))", again)), "3");
+}
+
+#[test]
+#[should_panic(expected = "macro expansion produced invalid code: (recur 1)")]
+fn macro_producing_recur_in_non_tail_position() {
+    eval("(defmacro again (x) `(recur ,x)) (loop (i 0) (list (again 1)))");
+}
+
+#[test]
+#[should_panic(expected = "macro expansion produced invalid code: (if 1)")]
+fn macro_producing_invalid_code() {
+    eval("(defmacro broken () '(if 1)) (broken)");
+}
+
+#[test]
+fn syntax_rules() {
+    let pairs = "(define-syntax sums (syntax-rules () ((_ (a b) ...) (list (+ a b) ...))))";
+    assert_eq!(eval(&format!("{} (sums (1 2) (3 4))", pairs)), "(3 7)");
+    assert_eq!(eval(&format!("{} (sums)", pairs)), "()");
+
+    let arrow = "(define-syntax arrow (syntax-rules (=>) ((_ a => b) (list a b)) ((_ a b) 'no-arrow)))";
+    assert_eq!(eval(&format!("{} (arrow 1 => 2)", arrow)), "(1 2)");
+    assert_eq!(eval(&format!("{} (arrow 1 2)", arrow)), "no-arrow");
+
+    let last = "(define-syntax last (syntax-rules () ((_ a ... b) 'b)))";
+    assert_eq!(eval(&format!("{} (last 1 2 3)", last)), "3");
+}
+
+#[test]
+fn syntax_rules_hygiene() {
+    let or = "(define-syntax my-or (syntax-rules () \
+        ((_) false) \
+        ((_ e) e) \
+        ((_ e r ...) (let (t e) (if t t (my-or r ...))))))";
+    assert_eq!(eval(&format!("{} (my-or false (= 1 1))", or)), "true");
+    // the t bound by the macro doesn't capture the t passed to it
+    assert_eq!(eval(&format!("{} (let (t true) (my-or false t))", or)), "true");
+    assert!(eval(&format!("{} (macroexpand '(my-or false true))", or)).starts_with("(let (#<t "));
+    // code read later can't refer to the renamed symbols
+    let renamed = format!("{} (define t (first (second (macroexpand '(my-or false true)))))", or);
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, &renamed);
+    assert!(eval_in(&mut interpreter, "(read (open-input-string (symbol->string t)))").starts_with("error: read:"));
+
+    // other introduced symbols refer to the bindings where the macro was defined, not where it's used
+    let pair = "(define-syntax pair (syntax-rules () ((_ a b) (list a b))))";
+    assert_eq!(eval(&format!("{} (let (list +) (pair 1 2))", pair)), "(1 2)");
+    let tenfold = "(define helper (lambda (x) (* x 10))) (define-syntax tenfold (syntax-rules () ((_ x) (helper x))))";
+    assert_eq!(eval(&format!("{} ((lambda (helper) (tenfold 2)) inc)", tenfold)), "20");
+    // but they see later definitions there
+    assert_eq!(eval(&format!("{} (define helper (lambda (x) (* x 100))) (tenfold 2)", tenfold)), "200");
+
+    // names defined by the expansion are renamed too
+    let define_t = "(define-syntax define-t (syntax-rules () ((_ v) (begin (define t v) t))))";
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, &format!("{} (define-t 1)", define_t)), "1");
+    assert_eq!(eval_in(&mut interpreter, "t"), "error: undefined ident: t");
+
+    // symbols in quoted data keep their name
+    let quoted = "(define-syntax q (syntax-rules () ((_ x) (list '(t list) `(t ,x)))))";
+    assert_eq!(eval(&format!("{} (q 1)", quoted)), "((t list) (t 1))");
+}
+
+#[test]
+#[should_panic(expected = "arrow: no syntax rule matches (arrow 1 2 3)")]
+fn syntax_rules_without_matching_rule() {
+    eval("(define-syntax arrow (syntax-rules (=>) ((_ a => b) (list a b)))) (arrow 1 2 3)");
+}
+
+#[test]
+fn tagged_literals() {
+    let double = "(register-tag 'double (lambda (x) (* 2 x)))";
+    assert_eq!(eval(&format!("{} #double 21", double)), "42");
+    assert_eq!(eval(&format!("{} '(a #double 2)", double)), "(a 4)");
+    assert_eq!(eval(&format!("{} (+ 1 #double #double 1)", double)), "5");
+
+    // the result is a literal, it isn't evaluated
+    let id = "(register-tag 'id (lambda (x) x))";
+    assert_eq!(eval(&format!("{} #id undefined", id)), "undefined");
+    assert_eq!(eval(&format!("{} (list #id (+ 1 2) '(#id (a)))", id)), "((+ 1 2) ((a)))");
+    assert_eq!(eval(&format!("{} `(#id x ,#id y)", id)), "(x y)");
+
+    // even if it can't be printed and read back
+    let adder = "(register-tag 'adder (lambda (n) (lambda (x) (+ x n))))";
+    assert_eq!(eval(&format!("{} (#adder 1 2)", adder)), "3");
+
+    let mut interpreter = Interpreter::new();
+    interpreter.register_fn("point", |x: i64, y: i64| HostObject::new("Point", (x, y)));
+    eval_in(&mut interpreter, "(register-tag 'point (lambda ((x y)) (point x y)))");
+    assert_eq!(eval_in(&mut interpreter, "#point(1 2)"), "#<host Point>");
+    assert_eq!(interpreter.eval_str("#point(1 2)").unwrap().downcast_host::<(i64, i64)>(), Some(&(1, 2)));
+}
+
+#[test]
+#[should_panic(expected = "no reader function for tag #nope")]
+fn tagged_literal_without_reader() {
+    eval("#nope 1");
+}
+
+#[test]
+fn conversions() {
+    use std::collections::BTreeMap;
+    use ::value::{FromValue, IntoValue};
+
+    let interner = &mut StringInterner::new();
+    assert_eq!(i64::from_value(&Value::new_integer(3)), Some(3));
+    assert_eq!(i64::from_value(&Value::new_bool(true)), None);
+    assert_eq!(<&str>::from_value(&Value::new_string("a")), Some("a"));
+    assert_eq!(Option::<char>::from_value(&Value::new_bool(false)), Some(None));
+    assert_eq!(Vec::<(i64, String)>::from_value(&vec![(1, "a".to_string())].into_value()), Some(vec![(1, "a".to_string())]));
+    assert_eq!(Vec::<i64>::from_value(&vec![Value::new_integer(1), Value::new_bool(true)].into_value()), None);
+
+    let mut map = BTreeMap::new();
+    map.insert('a', vec![true]);
+    map.insert('b', vec![]);
+    assert_eq!(map.clone().into_value().to_string(interner), r"((#\a (true)) (#\b ()))");
+    assert_eq!(BTreeMap::<char, Vec<bool>>::from_value(&map.clone().into_value()), Some(map));
+    assert_eq!(Vec::<(i64, Option<String>)>::type_name(), "list of (integer string or false)");
+    assert_eq!(Some((1, 'x', "s")).into_value().to_string(interner), r#"(1 #\x "s")"#);
+}
+
+#[test]
+fn register_fn() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register_fn("add", |a: i64, b: i64| a + b);
+    interpreter.register_fn("words", |s: String| s.split_whitespace().map(String::from).collect::<Vec<String>>());
+    interpreter.register_fn("lookup", |pairs: Vec<(String, i64)>, key: String| pairs.into_iter().find(|p| p.0 == key).map(|p| p.1));
+    interpreter.register_fn("checked-div", |a: i64, b: i64| if b == 0 { Err("division by zero") } else { Ok(a / b) });
+    interpreter.register_fn("nothing", || ());
+    interpreter.register_fn("shout", |s: &str| s.to_uppercase());
+    interpreter.register_fn("repeat", |n: i64, s: &str| s.repeat(n as usize));
+
+    assert_eq!(eval_in(&mut interpreter, "(add 1 2)"), "3");
+    assert_eq!(eval_in(&mut interpreter, r#"(words "a b  c")"#), r#"("a" "b" "c")"#);
+    assert_eq!(eval_in(&mut interpreter, r#"(list (lookup '(("a" 1)) "a") (lookup '() "a"))"#), "(1 false)");
+    assert_eq!(eval_in(&mut interpreter, "(nothing)"), "()");
+    assert_eq!(eval_in(&mut interpreter, r#"(shout "hi")"#), r#""HI""#);
+    assert_eq!(eval_in(&mut interpreter, r#"(repeat 3 "ab")"#), r#""ababab""#);
+    assert_eq!(eval_in(&mut interpreter, "(shout 1)"), "error: shout expected string, got: 1");
+    assert_eq!(eval_in(&mut interpreter, "(checked-div 4 2)"), "2");
+    assert_eq!(eval_in(&mut interpreter, "(checked-div 4 0)"), "error: division by zero");
+    assert_eq!(eval_in(&mut interpreter, "(add 1)"), "error: arity mismatch for add: expected: 2, got: 1");
+    assert_eq!(eval_in(&mut interpreter, r#"(add 1 "2")"#), r#"error: add expected integer, got: "2""#);
+    assert_eq!(eval_in(&mut interpreter, "(lookup '((a 1)) \"a\")"), "error: lookup expected list of (string integer), got: ((a 1))");
+}
@@ -1,12 +1,12 @@
 use std::rc::Rc;
 use std::borrow::Cow;
-use std::mem;
 use std::char;
+use std::any::Any;
 use super::value_data::*;
 use ::value::*;
-use ::interpreter::Interpreter;
 use ::scope::Scope;
 use ::string_interner::StringInterner;
+use ::port::Port;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Value {
@@ -25,15 +25,26 @@ impl Value {
     pub fn new_string<'a, T: 'a + Into<Cow<'a, str>>>(x: T) -> Self { Self::new_with(ValueData::String(x.into().into_owned())) }
     pub fn new_condition(x: Value) -> Self { Self::new_with(ValueData::Condition(x)) }
     pub fn empty_list() -> Self { Self::new_with(ValueData::EmptyList) }
-    pub fn new_native_proc(f: fn(&mut Interpreter, &mut [Value]) -> Value) -> Self {
-        let raw: *const () = f as *const ();
-        Self::new_with(ValueData::NativeProc(raw))
-    }
-    pub fn new_proc(name: Option<String>, parent_scope: Scope, bindings: Vec<u64>, code: Vec<Value>) -> Self {
+    pub fn new_native_proc(native: NativeProc) -> Self { Self::new_with(ValueData::NativeProc(native)) }
+    pub fn new_proc(name: Option<String>, parent_scope: Scope, bindings: Pattern, code: Vec<Value>) -> Self {
         let procedure = Proc::new(name, parent_scope, bindings, code);
         Self::new_with(ValueData::Proc(procedure))
     }
 
+    pub fn new_macro(name: String, parent_scope: Scope, bindings: Pattern, code: Vec<Value>) -> Self {
+        let procedure = Proc::new(Some(name), parent_scope, bindings, code);
+        Self::new_with(ValueData::Macro(procedure))
+    }
+
+    pub fn new_syntax_rules(rules: SyntaxRules, scope: Scope) -> Self { Self::new_with(ValueData::SyntaxRules(rules, scope)) }
+
+    pub fn new_tagged(tag: u64, datum: Value) -> Self { Self::new_with(ValueData::Tagged(tag, datum)) }
+
+    pub fn new_port(port: Port) -> Self { Self::new_with(ValueData::Port(port)) }
+    pub fn eof() -> Self { Self::new_with(ValueData::Eof) }
+    pub fn new_environment(scope: Scope) -> Self { Self::new_with(ValueData::Environment(scope)) }
+    pub fn new_host_object(object: HostObject) -> Self { Self::new_with(ValueData::HostObject(object)) }
+
     pub fn new_recur(args: Vec<Value>) -> Self { Self::new_with(ValueData::Recur(args)) }
     pub fn new_begin(code: Vec<Value>) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Begin(Begin::new(code))))
@@ -41,16 +52,22 @@ impl Value {
     pub fn new_define(symbol_id: u64, expression: Value) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Define(Define::new(symbol_id, expression))))
     }
+    pub fn new_defmacro(symbol_id: u64, bindings: Pattern, code: Vec<Value>) -> Self {
+        Self::new_with(ValueData::SpecialForm(SpecialForm::Defmacro(Defmacro::new(symbol_id, bindings, code))))
+    }
+    pub fn new_define_syntax(symbol_id: u64, spec: Value, rules: SyntaxRules) -> Self {
+        Self::new_with(ValueData::SpecialForm(SpecialForm::DefineSyntax(DefineSyntax::new(symbol_id, spec, rules))))
+    }
     pub fn new_if(condition: Value, then: Value, or_else: Value) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::If(If::new(condition, then, or_else))))
     }
-    pub fn new_lambda(name: Option<String>, bindings: Vec<u64>, code: Vec<Value>) -> Self {
+    pub fn new_lambda(name: Option<String>, bindings: Pattern, code: Vec<Value>) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Lambda(Lambda::new(name, bindings, code))))
     }
-    pub fn new_let(bindings: Vec<(u64, Value)>, code: Vec<Value>) -> Self {
+    pub fn new_let(bindings: Vec<(Pattern, Value)>, code: Vec<Value>) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Let(LetLoop::new(bindings, code))))
     }
-    pub fn new_loop(bindings: Vec<(u64, Value)>, code: Vec<Value>) -> Self {
+    pub fn new_loop(bindings: Vec<(Pattern, Value)>, code: Vec<Value>) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Loop(LetLoop::new(bindings, code))))
     }
     pub fn new_recur_form(bindings: Vec<Value>) -> Self {
@@ -59,11 +76,38 @@ impl Value {
     pub fn new_quote(expression: Value) -> Self {
         Self::new_with(ValueData::SpecialForm(SpecialForm::Quote(Quote::new(expression))))
     }
+    pub fn new_quasiquote(template: QuasiTemplate) -> Self {
+        Self::new_with(ValueData::SpecialForm(SpecialForm::Quasiquote(Quasiquote::new(template))))
+    }
+    pub fn new_match(expression: Value, clauses: Vec<MatchClause>) -> Self {
+        Self::new_with(ValueData::SpecialForm(SpecialForm::Match(Match::new(expression, clauses))))
+    }
 
     fn data(&self) -> &ValueData {
         &*self.val_ptr
     }
 
+    // identifies the shared data of the value, used by the cycle collector
+    pub fn rc_address(&self) -> usize {
+        &*self.val_ptr as *const ValueData as usize
+    }
+
+    pub fn rc_strong_count(&self) -> usize {
+        Rc::strong_count(&self.val_ptr)
+    }
+
+    // The values and scopes this value holds a reference to, for the cycle collector.
+    // Leaving some out only means that cycles through them aren't collected.
+    pub fn references(&self) -> (Vec<&Value>, Vec<&Scope>) {
+        match self.data() {
+            &ValueData::List(ref values) | &ValueData::Recur(ref values) => (values.iter().collect(), vec![]),
+            &ValueData::Condition(ref x) | &ValueData::Tagged(_, ref x) => (vec![x], vec![]),
+            &ValueData::Proc(ref p) | &ValueData::Macro(ref p) => (p.code().iter().collect(), vec![p.parent_scope()]),
+            &ValueData::Environment(ref scope) | &ValueData::SyntaxRules(_, ref scope) => (vec![], vec![scope]),
+            _ => (vec![], vec![]),
+        }
+    }
+
     pub fn get_empty_list(&self) -> Option<()> {
         if let &ValueData::EmptyList = self.data() { Some(()) } else { None }
     }
@@ -106,6 +150,15 @@ impl Value {
         }
     }
 
+    // without cloning the list like get_list
+    pub fn list_len(&self) -> Option<usize> {
+        match self.data() {
+            &ValueData::List(ref values) => Some(values.len()),
+            &ValueData::EmptyList => Some(0),
+            _ => None,
+        }
+    }
+
     pub fn get_string(&self) -> Option<&str> {
         match self.data() {
             &ValueData::String(ref s) => Some(s.as_str()),
@@ -120,9 +173,9 @@ impl Value {
         }
     }
 
-    pub fn get_native_fn_ptr(&self) -> Option<fn(&mut Interpreter, &mut [Value]) -> Value> {
+    pub fn get_native_proc(&self) -> Option<&NativeProc> {
         match self.data() {
-            &ValueData::NativeProc(f) => Some(unsafe { mem::transmute(f) }),
+            &ValueData::NativeProc(ref native) => Some(native),
             _ => None,
         }
     }
@@ -134,6 +187,57 @@ impl Value {
         }
     }
 
+    pub fn get_macro(&self) -> Option<&Proc> {
+        match self.data() {
+            &ValueData::Macro(ref p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn get_syntax_rules(&self) -> Option<(&SyntaxRules, &Scope)> {
+        match self.data() {
+            &ValueData::SyntaxRules(ref r, ref scope) => Some((r, scope)),
+            _ => None,
+        }
+    }
+
+    pub fn get_tagged(&self) -> Option<(u64, &Value)> {
+        match self.data() {
+            &ValueData::Tagged(tag, ref datum) => Some((tag, datum)),
+            _ => None,
+        }
+    }
+
+    pub fn get_port(&self) -> Option<&Port> {
+        match self.data() {
+            &ValueData::Port(ref p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn get_eof(&self) -> Option<()> {
+        if let &ValueData::Eof = self.data() { Some(()) } else { None }
+    }
+
+    pub fn get_environment(&self) -> Option<&Scope> {
+        match self.data() {
+            &ValueData::Environment(ref scope) => Some(scope),
+            _ => None,
+        }
+    }
+
+    pub fn get_host_object(&self) -> Option<&HostObject> {
+        match self.data() {
+            &ValueData::HostObject(ref x) => Some(x),
+            _ => None,
+        }
+    }
+
+    // the object, if it is a host object holding a T
+    pub fn downcast_host<T: Any>(&self) -> Option<&T> {
+        self.get_host_object().and_then(HostObject::downcast)
+    }
+
     pub fn get_special_form(&self) -> Option<&SpecialForm> {
         match self.data() {
             &ValueData::SpecialForm(ref s) => Some(s),
@@ -155,4 +259,17 @@ impl Value {
     pub fn to_string(&self, interner: &StringInterner) -> String {
         self.data().to_string(interner)
     }
+
+    pub fn to_display_string(&self, interner: &StringInterner) -> String {
+        self.data().to_display_string(interner)
+    }
+
+    // code as data, i.e. special forms are turned into lists again
+    pub fn to_data(&self, interner: &mut StringInterner) -> Value {
+        match self.data() {
+            &ValueData::SpecialForm(ref x) => x.to_data(interner),
+            &ValueData::List(ref values) => Value::new_list(&values.iter().map(|x| x.to_data(interner)).collect::<Vec<_>>()),
+            _ => self.clone(),
+        }
+    }
 }
@@ -1,6 +1,8 @@
-use ::value::{Value, Proc, SpecialForm};
+use ::value::{Value, Proc, NativeProc, HostObject, SpecialForm, SyntaxRules};
 use ::string_interner::StringInterner;
-use grammar::escape_char;
+use ::port::Port;
+use ::scope::Scope;
+use grammar::{escape_char, escape_string};
 use itertools::Itertools;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -13,9 +15,17 @@ pub enum ValueData {
     EmptyList,
     List(Vec<Value>),
     Condition(Value),
-    NativeProc(*const ()),
+    NativeProc(NativeProc),
     Proc(Proc),
+    Macro(Proc),
+    // with the scope they were defined in
+    SyntaxRules(SyntaxRules, Scope),
     Recur(Vec<Value>),
+    Tagged(u64, Value),
+    Port(Port),
+    Environment(Scope),
+    Eof,
+    HostObject(HostObject),
     SpecialForm(SpecialForm),
 }
 
@@ -32,14 +42,31 @@ impl ValueData {
             },
             &ValueData::Integer(x) => format!("{}", x),
             &ValueData::Symbol(id) => format!("{}", interner.lookup(id).unwrap_or(&format!("[SYMBOL: {}]", id.to_string()))),
-            &ValueData::String(ref x) => format!("\"{}\"", x),
+            &ValueData::String(ref x) => format!("\"{}\"", escape_string(x)),
             &ValueData::Condition(ref x) => format!("[CONDITION: {:?}]", x),
             &ValueData::EmptyList => format!("()"),
             &ValueData::List(ref values) => format!("({})", values.iter().map(|v| v.to_string(interner)).join(" ")),
-            &ValueData::NativeProc(x) => format!("[NATIVE_PROC: {:?}]", x),
+            &ValueData::NativeProc(ref x) => format!("[NATIVE_PROC: {}]", x.name()),
             &ValueData::Proc(ref p) => format!("[PROC: {}]", p.to_string(interner)),
+            &ValueData::Macro(ref p) => format!("[MACRO: {}]", p.to_string(interner)),
+            &ValueData::SyntaxRules(..) => format!("[SYNTAX_RULES]"),
+            &ValueData::Tagged(tag, ref x) => format!("#{} {}", Value::new_symbol(tag).to_string(interner), x.to_string(interner)),
             &ValueData::Recur(ref p) => format!("[RECUR: {}]", Value::new_list(&p).to_string(interner)),
-            &ValueData::SpecialForm(_) => unimplemented!(),
+            &ValueData::Port(ref x) => x.to_string(),
+            &ValueData::Eof => format!("[EOF]"),
+            &ValueData::Environment(_) => format!("[ENVIRONMENT]"),
+            &ValueData::HostObject(ref x) => x.to_string(),
+            &ValueData::SpecialForm(ref x) => x.to_string(interner),
+        }
+    }
+
+    // like to_string, but strings and chars aren't quoted or escaped
+    pub fn to_display_string(&self, interner: &StringInterner) -> String {
+        match self {
+            &ValueData::Char(x) => x.to_string(),
+            &ValueData::String(ref x) => x.clone(),
+            &ValueData::List(ref values) => format!("({})", values.iter().map(|v| v.to_display_string(interner)).join(" ")),
+            _ => self.to_string(interner),
         }
     }
 }